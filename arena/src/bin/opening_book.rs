@@ -0,0 +1,61 @@
+//! Generates opening books for every two player map, see [game_logic::opening_book::generate]
+//!
+//! The default books are regenerated with
+//! `cargo run --release -p arena --bin opening_book -- game_logic/books/default_books.json`
+
+use std::{collections::BTreeMap, fs, process};
+
+use game_logic::{
+    game_mode::TwoPlayerMap,
+    opening_book::{self, BookConfig, OpeningBook},
+};
+
+const USAGE: &str =
+    "usage: opening_book <output.json> [--plies N] [--depth N] [--candidates N] [--tolerance X]";
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(String, BookConfig), String> {
+    let mut output = None;
+    let mut config = BookConfig::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{flag} needs a value"));
+        match arg.as_str() {
+            "--plies" => config.plies = value("--plies")?.parse().map_err(|e| format!("{e}"))?,
+            "--depth" => config.depth = value("--depth")?.parse().map_err(|e| format!("{e}"))?,
+            "--candidates" => {
+                config.candidates = value("--candidates")?.parse().map_err(|e| format!("{e}"))?
+            }
+            "--tolerance" => {
+                config.tolerance = value("--tolerance")?.parse().map_err(|e| format!("{e}"))?
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ if output.is_none() => output = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    output
+        .map(|output| (output, config))
+        .ok_or(USAGE.to_string())
+}
+
+fn main() {
+    let (output, config) = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+
+    let books: BTreeMap<TwoPlayerMap, OpeningBook> = TwoPlayerMap::ALL
+        .into_iter()
+        .map(|map| {
+            let book = opening_book::generate(map, &config);
+            eprintln!("{map:?}: {} positions", book.positions.len());
+            (map, book)
+        })
+        .collect();
+
+    fs::write(&output, serde_json::to_string(&books).unwrap()).unwrap_or_else(|e| {
+        eprintln!("failed to write {output}: {e}");
+        process::exit(1);
+    });
+}
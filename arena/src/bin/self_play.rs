@@ -0,0 +1,97 @@
+//! Writes self-play games as line-delimited JSON training data, one [TrainingRecord](arena::training::TrainingRecord) per position
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+};
+
+use arena::{parse_strategy, training};
+use game_logic::{game_mode::TwoPlayerMap, parallel};
+
+const USAGE: &str = "usage: self_play <strategy> [--games N] [--seed S] [--output file.jsonl]
+
+plays N games (default 10) on every two player map with the strategy playing both sides
+strategies: search, search:<depth>, or a registered strategy such as greedy or random
+writes to stdout unless an output file is given";
+
+struct Args {
+    strategy: String,
+    games: u32,
+    seed: u64,
+    output: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut strategy = None;
+    let mut games = 10;
+    let mut seed = 0;
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{flag} needs a value"));
+        match arg.as_str() {
+            "--games" => games = value("--games")?.parse().map_err(|e| format!("{e}"))?,
+            "--seed" => seed = value("--seed")?.parse().map_err(|e| format!("{e}"))?,
+            "--output" => output = Some(value("--output")?),
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ if strategy.is_none() => strategy = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok(Args {
+        strategy: strategy.ok_or(USAGE.to_string())?,
+        games,
+        seed,
+        output,
+    })
+}
+
+fn main() -> io::Result<()> {
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+    let strategy = parse_strategy(&args.strategy).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+
+    let mut out: BufWriter<Box<dyn Write>> = BufWriter::new(match &args.output {
+        Some(path) => Box::new(File::create(path).unwrap_or_else(|e| {
+            eprintln!("failed to create {path}: {e}");
+            process::exit(1);
+        })),
+        None => Box::new(io::stdout()),
+    });
+
+    let games: Vec<(TwoPlayerMap, u32)> = TwoPlayerMap::ALL
+        .into_iter()
+        .flat_map(|map| std::iter::repeat_n(map, args.games as usize))
+        .zip(0..)
+        .collect();
+    // played a batch at a time on a thread pool with the parallel feature, and one at a time without.
+    // Each batch is written in order once its games finish
+    let written = games.chunks(parallel::threads()).try_for_each(|batch| {
+        let played = parallel::map(batch.to_vec(), |(map, game)| {
+            training::self_play(
+                map,
+                strategy.as_ref(),
+                game,
+                args.seed.wrapping_add(game as u64),
+            )
+        });
+        for record in played.iter().flatten() {
+            serde_json::to_writer(&mut out, record)?;
+            writeln!(out)?;
+        }
+        out.flush()
+    });
+
+    match written {
+        // the reader stopped early, e.g. piped into head
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => written,
+    }
+}
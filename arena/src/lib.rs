@@ -0,0 +1,260 @@
+//! Plays AI strategies against each other and reports how they fared
+//!
+//! Each match is a number of games on one map. The strategies alternate moving first and every game is seeded differently, so [Strategy]s that break ties randomly play different games
+
+use game_logic::{
+    evaluation::Weights,
+    game_mode::{GameMode, TwoPlayerMap},
+    game_result::Outcome,
+    game_state::GameState,
+    parallel,
+    player::Player,
+    strategy::{self, AlphaBeta, Strategy},
+};
+use serde::Serialize;
+
+pub mod training;
+
+/// depth searched by the "search" strategy when none is given
+pub const DEFAULT_SEARCH_DEPTH: u8 = 2;
+
+/// Builds a strategy from its name on the command line
+///
+/// `search` and `search:<depth>` are an alpha-beta search, any other name is looked up in the [strategy] registry, e.g. `greedy` or `random`
+pub fn parse_strategy(name: &str) -> Result<Box<dyn Strategy>, String> {
+    let weights = Weights::default();
+    match name.split_once(':') {
+        Some(("search", depth)) => depth
+            .parse()
+            .map(|depth| Box::new(AlphaBeta { depth, weights }) as Box<dyn Strategy>)
+            .map_err(|_| format!("invalid search depth: {depth}")),
+        None if name == "search" => Ok(Box::new(AlphaBeta {
+            depth: DEFAULT_SEARCH_DEPTH,
+            weights,
+        })),
+        _ => strategy::by_name(name).ok_or_else(|| {
+            format!(
+                "unknown strategy: {name}, expected search, search:<depth> or one of {}",
+                strategy::names().join(", ")
+            )
+        }),
+    }
+}
+
+/// A finished game, from the first strategy's point of view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameSummary {
+    pub outcome: MatchOutcome,
+    /// the first strategy's final score minus the second's
+    pub margin: i8,
+    /// turns taken, including passes
+    pub turns: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchOutcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Plays one game on a map, `a_first` decides whether strategy `a` moves first
+pub fn play_game(
+    map: TwoPlayerMap,
+    a: &dyn Strategy,
+    b: &dyn Strategy,
+    a_first: bool,
+    seed: u64,
+) -> GameSummary {
+    let mut gs = GameState::new(GameMode::TwoPlayer(map)).with_seed(seed);
+    let a_player = if a_first { Player::P1 } else { Player::P2 };
+
+    let mut turns = 0;
+    while !gs.game_ended() {
+        let strategy = if gs.current_player() == a_player {
+            a
+        } else {
+            b
+        };
+        gs.make_ai_move(strategy).expect("the game has not ended");
+        turns += 1;
+    }
+
+    let outcome = match gs.result().map(|result| &result.outcome) {
+        Some(Outcome::Winner(player)) if *player == a_player => MatchOutcome::Win,
+        Some(Outcome::Winner(_)) => MatchOutcome::Loss,
+        _ => MatchOutcome::Draw,
+    };
+
+    GameSummary {
+        outcome,
+        margin: gs.score_margin(a_player),
+        turns,
+    }
+}
+
+/// The results of a match on one map, from the first strategy's point of view
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MatchStats {
+    pub map: String,
+    pub strategy_a: String,
+    pub strategy_b: String,
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub win_rate: f32,
+    pub draw_rate: f32,
+    pub loss_rate: f32,
+    pub average_margin: f32,
+    pub average_length: f32,
+}
+
+impl MatchStats {
+    /// Sums up the games of a match
+    pub fn from_games(
+        map: TwoPlayerMap,
+        strategy_a: &str,
+        strategy_b: &str,
+        games: &[GameSummary],
+    ) -> Self {
+        let count = |outcome| games.iter().filter(|g| g.outcome == outcome).count() as u32;
+        let (wins, draws, losses) = (
+            count(MatchOutcome::Win),
+            count(MatchOutcome::Draw),
+            count(MatchOutcome::Loss),
+        );
+        let total = games.len().max(1) as f32;
+
+        Self {
+            map: format!("{map:?}"),
+            strategy_a: strategy_a.to_string(),
+            strategy_b: strategy_b.to_string(),
+            games: games.len() as u32,
+            wins,
+            draws,
+            losses,
+            win_rate: wins as f32 / total,
+            draw_rate: draws as f32 / total,
+            loss_rate: losses as f32 / total,
+            average_margin: games.iter().map(|g| g.margin as f32).sum::<f32>() / total,
+            average_length: games.iter().map(|g| g.turns as f32).sum::<f32>() / total,
+        }
+    }
+}
+
+/// Plays `games` games on a map, alternating which strategy moves first
+///
+/// Game `i` is seeded with `seed + i`
+pub fn play_match(
+    map: TwoPlayerMap,
+    (a_name, a): (&str, &dyn Strategy),
+    (b_name, b): (&str, &dyn Strategy),
+    games: u32,
+    seed: u64,
+) -> MatchStats {
+    // played on a thread pool with the parallel feature, the games do not depend on each other
+    let summaries = parallel::map((0..games).collect(), |i| {
+        play_game(map, a, b, i % 2 == 0, seed.wrapping_add(i as u64))
+    });
+
+    MatchStats::from_games(map, a_name, b_name, &summaries)
+}
+
+/// How the stats are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format: {s}, expected json or csv")),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "map,strategy_a,strategy_b,games,wins,draws,losses,win_rate,draw_rate,loss_rate,average_margin,average_length";
+
+/// Formats the stats of every match
+pub fn format_stats(stats: &[MatchStats], format: Format) -> String {
+    match format {
+        Format::Json => serde_json::to_string_pretty(stats).expect("stats serialize"),
+        Format::Csv => std::iter::once(CSV_HEADER.to_string())
+            .chain(stats.iter().map(|s| {
+                format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{}",
+                    s.map,
+                    s.strategy_a,
+                    s.strategy_b,
+                    s.games,
+                    s.wins,
+                    s.draws,
+                    s.losses,
+                    s.win_rate,
+                    s.draw_rate,
+                    s.loss_rate,
+                    s.average_margin,
+                    s.average_length
+                )
+            }))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game_logic::strategy::{Greedy, Random};
+
+    use super::*;
+
+    #[test]
+    fn match_stats() {
+        let stats = play_match(
+            TwoPlayerMap::Tower,
+            ("greedy", &Greedy),
+            ("random", &Random),
+            4,
+            0,
+        );
+
+        assert_eq!(stats.games, 4);
+        assert_eq!(stats.wins + stats.draws + stats.losses, 4);
+        assert_eq!(stats.win_rate + stats.draw_rate + stats.loss_rate, 1.0);
+        assert!(stats.average_length > 0.0);
+        assert_eq!(
+            play_match(
+                TwoPlayerMap::Tower,
+                ("greedy", &Greedy),
+                ("random", &Random),
+                4,
+                0
+            ),
+            stats
+        );
+
+        let csv = format_stats(std::slice::from_ref(&stats), Format::Csv);
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("Tower,greedy,random,4,"));
+    }
+
+    #[test]
+    fn parse_strategies() {
+        assert!(parse_strategy("greedy").is_ok());
+        assert!(parse_strategy("search").is_ok());
+        assert!(parse_strategy("search:3").is_ok());
+        assert!(parse_strategy("search:deep").is_err());
+        assert!(parse_strategy("unknown").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use std::process;
+
+use arena::{format_stats, parse_strategy, play_match, Format, MatchStats};
+use game_logic::game_mode::TwoPlayerMap;
+
+const USAGE: &str =
+    "usage: arena <strategy_a> <strategy_b> [--games N] [--seed S] [--format json|csv]
+
+strategies: search, search:<depth>, or a registered strategy such as greedy or random
+plays N games (default 10) on every two player map, alternating who moves first";
+
+struct Args {
+    strategy_a: String,
+    strategy_b: String,
+    games: u32,
+    seed: u64,
+    format: Format,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut strategies = vec![];
+    let mut games = 10;
+    let mut seed = 0;
+    let mut format = Format::Json;
+
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or(format!("{flag} needs a value"));
+        match arg.as_str() {
+            "--games" => games = value("--games")?.parse().map_err(|e| format!("{e}"))?,
+            "--seed" => seed = value("--seed")?.parse().map_err(|e| format!("{e}"))?,
+            "--format" => format = value("--format")?.parse()?,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => strategies.push(arg),
+        }
+    }
+
+    match <[String; 2]>::try_from(strategies) {
+        Ok([strategy_a, strategy_b]) => Ok(Args {
+            strategy_a,
+            strategy_b,
+            games,
+            seed,
+            format,
+        }),
+        Err(_) => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(2);
+    });
+    let (a, b) = match (
+        parse_strategy(&args.strategy_a),
+        parse_strategy(&args.strategy_b),
+    ) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("{e}");
+            process::exit(2);
+        }
+    };
+
+    let stats: Vec<MatchStats> = TwoPlayerMap::ALL
+        .into_iter()
+        .map(|map| {
+            play_match(
+                map,
+                (&args.strategy_a, a.as_ref()),
+                (&args.strategy_b, b.as_ref()),
+                args.games,
+                args.seed,
+            )
+        })
+        .collect();
+
+    println!("{}", format_stats(&stats, args.format));
+}
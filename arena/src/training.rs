@@ -0,0 +1,142 @@
+//! Records self-play games as training data for a learned evaluator
+//!
+//! Games are played through [GameState], so every position and move follows the real rules. Each position becomes one [TrainingRecord], written as a line of JSON
+
+use std::collections::BTreeMap;
+
+use game_logic::{
+    action::Action,
+    bitboard::{Bitboard, SIZE},
+    game_mode::{GameMode, TwoPlayerMap},
+    game_move::Move,
+    game_state::GameState,
+    piece::PieceName,
+    player::Player,
+    strategy::Strategy,
+};
+use serde::Serialize;
+
+/// channels of the board tensor: the cubes of each of the four players, then the cells of the map
+pub const CHANNELS: usize = Player::ALL.len() + 1;
+
+/// The shape of [TrainingRecord::board], (channel, y, x, z)
+pub const BOARD_SHAPE: [usize; 4] = [CHANNELS, SIZE, SIZE, SIZE];
+
+/// One position of a self-play game
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrainingRecord {
+    pub map: TwoPlayerMap,
+    pub game: u32,
+    /// turns taken before the position
+    pub turn: u32,
+    /// the position as 0 / 1 cells in [BOARD_SHAPE], flattened in row-major order
+    pub board: Vec<u8>,
+    pub side_to_move: Player,
+    /// the pieces each player has yet to play
+    pub remaining_pieces: BTreeMap<Player, Vec<PieceName>>,
+    /// None if the side to move passed
+    pub chosen_move: Option<Move>,
+    /// the side to move's reward from the finished game: 1 for a win, shared for a draw, 0 for a loss
+    pub outcome: f32,
+    /// the side to move's final score minus the best other final score
+    pub final_margin: i8,
+}
+
+/// Encodes the board in [BOARD_SHAPE]
+pub fn board_tensor(state: &GameState) -> Vec<u8> {
+    let board = state.board();
+    let channels: Vec<Bitboard> = Player::ALL
+        .into_iter()
+        .map(|player| board.occupied_by(player))
+        .chain(std::iter::once(board.in_bounds()))
+        .collect();
+
+    // a bitboard layer holds the cell (x, z) at bit x * SIZE + z, the tensor's order within a layer
+    channels
+        .iter()
+        .flat_map(|channel| (0..SIZE).map(|y| channel.layer(y)))
+        .flat_map(|layer| (0..SIZE * SIZE).map(move |bit| ((layer >> bit) & 1) as u8))
+        .collect()
+}
+
+/// Plays a game on a map with the same strategy for both players, returns a record for every position
+pub fn self_play(
+    map: TwoPlayerMap,
+    strategy: &dyn Strategy,
+    game: u32,
+    seed: u64,
+) -> Vec<TrainingRecord> {
+    let game_mode = GameMode::TwoPlayer(map);
+    let mut gs = GameState::new(game_mode).with_seed(seed);
+    let mut records = vec![];
+
+    while !gs.game_ended() {
+        let side_to_move = gs.current_player();
+        let chosen_move = strategy.choose_move(&gs);
+        records.push(TrainingRecord {
+            map,
+            game,
+            turn: records.len() as u32,
+            board: board_tensor(&gs),
+            side_to_move,
+            remaining_pieces: game_mode
+                .players()
+                .into_iter()
+                .map(|player| (player, gs.remaining_pieces(player)))
+                .collect(),
+            chosen_move: chosen_move.clone(),
+            outcome: 0.0,
+            final_margin: 0,
+        });
+
+        match chosen_move {
+            Some(game_move) => gs.play_move(&game_move),
+            None => gs.apply_action(Action::PassTurn),
+        }
+        .expect("the strategy's move can be played");
+    }
+
+    let result = gs.result().expect("the game has ended");
+    for record in &mut records {
+        record.outcome = result.reward(record.side_to_move);
+        record.final_margin = gs.score_margin(record.side_to_move);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use game_logic::strategy::Greedy;
+
+    use super::*;
+
+    #[test]
+    fn self_play_records() {
+        let records = self_play(TwoPlayerMap::Tower, &Greedy, 0, 0);
+        assert!(!records.is_empty());
+
+        let first = &records[0];
+        assert_eq!(first.board.len(), BOARD_SHAPE.iter().product::<usize>());
+        // the start position has the map's cells and no cubes
+        let cells = SIZE * SIZE * SIZE;
+        assert!(first.board[..4 * cells].iter().all(|cell| *cell == 0));
+        assert!(first.board[4 * cells..].contains(&1));
+        assert_eq!(
+            first.remaining_pieces[&Player::P1].len(),
+            PieceName::ALL.len()
+        );
+
+        // the cube placed by the first move appears in the second position
+        let cube = first.chosen_move.as_ref().unwrap().cubes()[0];
+        let index = (cube.y as usize * SIZE + cube.x as usize) * SIZE + cube.z as usize;
+        assert_eq!(records[1].board[index], 1);
+
+        // both sides share the result of a two player game
+        let total: f32 = records[..2].iter().map(|record| record.outcome).sum();
+        assert_eq!(total, 1.0);
+        assert_eq!(records[0].final_margin, -records[1].final_margin);
+
+        let line = serde_json::to_string(first).unwrap();
+        assert!(!line.contains('\n'));
+    }
+}
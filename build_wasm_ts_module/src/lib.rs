@@ -2,9 +2,19 @@
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use game_logic::{
-    action::Action,
+    action::{Action, ActionOutcome},
+    endgame,
+    evaluation::Weights,
     game_mode,
+    game_record::{GameRecord, RecordOutcome},
     game_state::{self, GameState},
+    hints,
+    mcts::{self, MctsConfig},
+    piece::PieceName,
+    player::Player,
+    rules::Rules,
+    search::{self, SearchBudget},
+    strategy,
 };
 
 /// used for generating a new game from WASM
@@ -20,6 +30,22 @@ pub fn new_game(game_mode_str: &str) -> String {
     }
 }
 
+/// used for generating a new game played with a variant rule set from WASM
+///
+/// Takes in a game mode and Rules as &str's
+#[wasm_bindgen]
+pub fn new_game_with_rules(game_mode_str: &str, rules_str: &str) -> String {
+    let game_mode = serde_json::from_str::<game_mode::GameMode>(game_mode_str);
+    let rules = serde_json::from_str::<Rules>(rules_str);
+
+    match (game_mode, rules) {
+        (Ok(game_mode), Ok(rules)) => {
+            serde_json::to_string(&GameState::with_rules(game_mode, rules)).unwrap()
+        }
+        _ => "Error".to_string(),
+    }
+}
+
 /// Given a GameState and Action as &str's in WASM, returns the resulting GameState (as String)
 #[wasm_bindgen]
 pub fn next_game_state(current_state_s: &str, action_s: &str) -> String {
@@ -28,13 +54,200 @@ pub fn next_game_state(current_state_s: &str, action_s: &str) -> String {
 
     match (current_state, action) {
         (Ok(mut cs), Ok(action)) => {
-            cs.apply_action(action);
+            // an action that cannot be applied leaves the state unchanged
+            let _ = cs.apply_action(action);
             serde_json::to_string(&cs).unwrap()
         }
         _ => "invalid".to_string(),
     }
 }
 
+/// Given a GameState and Action as &str's in WASM, returns an ActionOutcome (as String)
+///
+/// The outcome contains either the resulting GameState or the ActionError explaining why the action could not be applied
+#[wasm_bindgen]
+pub fn try_next_game_state(current_state_s: &str, action_s: &str) -> String {
+    let current_state = serde_json::from_str::<game_state::GameState>(current_state_s);
+    let action = serde_json::from_str::<Action>(action_s);
+
+    match (current_state, action) {
+        (Ok(mut cs), Ok(action)) => {
+            let outcome = match cs.apply_action(action) {
+                Ok(()) => ActionOutcome::Ok(Box::new(cs)),
+                Err(e) => ActionOutcome::Err(e),
+            };
+            serde_json::to_string(&outcome).unwrap()
+        }
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState and Player as &str's in WASM, returns the player's legal moves as an array of Move (as String)
+#[wasm_bindgen]
+pub fn legal_moves(current_state_s: &str, player_s: &str) -> String {
+    let current_state = serde_json::from_str::<game_state::GameState>(current_state_s);
+    let player = serde_json::from_str::<Player>(player_s);
+
+    match (current_state, player) {
+        (Ok(cs), Ok(player)) => serde_json::to_string(&cs.legal_moves(player)).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState and MctsConfig as &str's in WASM, returns the Move found by a Monte Carlo Tree Search for the current player (as String)
+///
+/// Returns null if the current player has no legal move
+#[wasm_bindgen]
+pub fn mcts_move(current_state_s: &str, config_s: &str) -> String {
+    let current_state = serde_json::from_str::<game_state::GameState>(current_state_s);
+    let config = serde_json::from_str::<MctsConfig>(config_s);
+
+    match (current_state, config) {
+        (Ok(cs), Ok(config)) => serde_json::to_string(&mcts::mcts(&cs, &config).best_move).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState as &str and a count in WASM, returns up to `count` Hints for the current player, best first (as String)
+///
+/// Each hint's cubes can be shown with the previewed piece
+#[wasm_bindgen]
+pub fn move_hints(current_state_s: &str, count: usize) -> String {
+    match serde_json::from_str::<game_state::GameState>(current_state_s) {
+        Ok(cs) => serde_json::to_string(&hints::hints(&cs, count, &Weights::default())).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState as &str in WASM, returns the Solution of the position for the current player (as String)
+///
+/// Returns null unless at most `threshold` legal moves are left, larger endgames take too long to solve
+#[wasm_bindgen]
+pub fn solve_endgame(current_state_s: &str, threshold: usize) -> String {
+    match serde_json::from_str::<game_state::GameState>(current_state_s) {
+        Ok(cs) => serde_json::to_string(
+            &endgame::is_solvable(&cs, threshold).then(|| endgame::solve(&cs)),
+        )
+        .unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState as &str in WASM, returns an Annotation for every turn of its history taken with at most `threshold` legal moves left (as String)
+#[wasm_bindgen]
+pub fn annotate_game(current_state_s: &str, threshold: usize) -> String {
+    match serde_json::from_str::<game_state::GameState>(current_state_s) {
+        Ok(cs) => serde_json::to_string(&endgame::annotate(&cs, threshold)).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a GameState as &str in WASM, returns the game written in the record notation, see [game_record](game_logic::game_record)
+#[wasm_bindgen]
+pub fn write_game_record(current_state_s: &str) -> String {
+    match serde_json::from_str::<game_state::GameState>(current_state_s) {
+        Ok(cs) => GameRecord::from_game(&cs).write(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a game record as &str in WASM, returns a RecordOutcome with the replayed GameState or where the record is wrong (as String)
+#[wasm_bindgen]
+pub fn read_game_record(record: &str) -> String {
+    let outcome = match GameRecord::parse_and_replay(record) {
+        Ok((_, game)) => RecordOutcome::Ok(Box::new(game)),
+        Err(e) => RecordOutcome::Err(e),
+    };
+    serde_json::to_string(&outcome).unwrap()
+}
+
+/// Given a GameState and SearchBudget as &str's in WASM, returns the Move found by an iterative deepening search within the budget for the current player (as String)
+///
+/// Returns null if the current player has no legal move
+#[wasm_bindgen]
+pub fn anytime_search_move(current_state_s: &str, budget_s: &str) -> String {
+    let current_state = serde_json::from_str::<game_state::GameState>(current_state_s);
+    let budget = serde_json::from_str::<SearchBudget>(budget_s);
+
+    match (current_state, budget) {
+        (Ok(cs), Ok(budget)) => {
+            serde_json::to_string(&search::anytime(&cs, &Weights::default(), budget).best_move)
+                .unwrap()
+        }
+        _ => "invalid".to_string(),
+    }
+}
+
+/// An iterative deepening search for the current player's move, run a step at a time so the page stays responsive
+///
+/// Drive it with `step` from a worker or `requestIdleCallback`, and play `best_move` whenever it is time to move
+#[wasm_bindgen]
+pub struct AnytimeSearch {
+    search: search::AnytimeSearch,
+}
+
+#[wasm_bindgen]
+impl AnytimeSearch {
+    /// Given a GameState as &str, starts searching for the current player's move
+    ///
+    /// Returns undefined if the state is invalid
+    pub fn start(current_state_s: &str) -> Option<AnytimeSearch> {
+        serde_json::from_str::<game_state::GameState>(current_state_s)
+            .ok()
+            .map(|cs| AnytimeSearch {
+                search: search::AnytimeSearch::start(&cs, Weights::default()),
+            })
+    }
+
+    /// Searches until about `nodes` positions have been expanded, returns whether there is more to search
+    pub fn step(&mut self, nodes: u32) -> bool {
+        self.search.step(nodes as u64)
+    }
+
+    /// The best Move found so far (as String), null if the player has no legal move
+    pub fn best_move(&self) -> String {
+        serde_json::to_string(&self.search.best_move()).unwrap()
+    }
+
+    /// The depth in plies of the deepest completed search, for a thinking indicator
+    pub fn depth(&self) -> u8 {
+        self.search.depth()
+    }
+
+    /// Positions visited so far
+    pub fn nodes(&self) -> f64 {
+        self.search.nodes() as f64
+    }
+}
+
+/// Returns the names of the registered AI strategies as an array of strings (as String)
+#[wasm_bindgen]
+pub fn strategy_names() -> String {
+    serde_json::to_string(&strategy::names()).unwrap()
+}
+
+/// Given a GameState and strategy name as &str's in WASM, returns the Move the strategy chooses for the current player (as String)
+///
+/// Returns null if the strategy passes
+#[wasm_bindgen]
+pub fn strategy_move(current_state_s: &str, strategy_name: &str) -> String {
+    let current_state = serde_json::from_str::<game_state::GameState>(current_state_s);
+
+    match (current_state, strategy::by_name(strategy_name)) {
+        (Ok(cs), Some(strategy)) => serde_json::to_string(&strategy.choose_move(&cs)).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
+/// Given a PieceName as &str in WASM, returns its distinct orientations as an array of Piece indexed by orientation id (as String)
+#[wasm_bindgen]
+pub fn piece_orientations(piece_name_s: &str) -> String {
+    match serde_json::from_str::<PieceName>(piece_name_s) {
+        Ok(piece_name) => serde_json::to_string(piece_name.orientations()).unwrap(),
+        _ => "invalid".to_string(),
+    }
+}
+
 #[wasm_bindgen]
 extern "C" {
     // Use `js_namespace` here to bind `console.log(..)` instead of just
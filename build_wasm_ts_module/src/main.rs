@@ -93,7 +93,7 @@ fn add_types_to_package_json() {
 fn move_dir_to_build_wasm_bundle_project() {
     if let Ok(true) = fs::exists(BUILD_PROJECT.to_string() + "/pkg/types") {
         fs::remove_dir_all(BUILD_PROJECT.to_string() + "/pkg/types")
-            .expect(format!("failed to delete existing dir:  {}/pkg", BUILD_PROJECT).as_str());
+            .unwrap_or_else(|_| panic!("failed to delete existing dir:  {}/pkg", BUILD_PROJECT));
     }
     fs::rename(
         GAME_LOGIC.to_string() + "/pkg/types",
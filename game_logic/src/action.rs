@@ -1,8 +1,10 @@
-use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::{piece::PieceName, rotation_axis::RotationAxis};
+use crate::{
+    board::CubeError, coord::Coord, game_mode::GameMode, game_state::GameState, mcts::MctsConfig,
+    piece::PieceName, rotation_axis::RotationAxis,
+};
 
 /// Action enum defines all actions that could be performed by a player
 ///
@@ -20,11 +22,62 @@ pub enum Action {
     PassTurn,
     Reset,
     MakeGreedyAIMove,
+    /// plays the AI opponent's move at the difficulty chosen for the game
+    MakeAIMove,
+    /// plays the move found by a depth-limited alpha-beta search, the depth in plies up to [search::MAX_DEPTH](crate::search::MAX_DEPTH)
+    MakeSearchAIMove(u8),
+    /// plays the move found by a Monte Carlo Tree Search
+    MakeMCTSAIMove(MctsConfig),
+    /// plays the move chosen by the strategy registered under the name, see [strategy](crate::strategy)
+    MakeStrategyAIMove(String),
+    Undo,
+    Redo,
 }
 
-/// A newtype wrapper around a [`nalgebra::Vector3<f32>`]
+/// Reasons an [Action] could not be applied
+///
+/// Returned from [GameState::apply_action]. The game state is left unchanged when an error is returned
+#[derive(Serialize, Deserialize, TS, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/ActionError.ts")]
+pub enum ActionError {
+    /// the action requires a selected piece but none is selected
+    NoPieceSelected,
+    /// the action requires a previewed piece but none is previewed
+    NoPiecePreviewed,
+    /// the piece has already been played and is no longer in the player's hand
+    PieceAlreadyPlayed(PieceName),
+    /// the previewed placement is not a legal move, contains the errors found in its cubes
+    InvalidPreview(Vec<CubeError>),
+    /// the orientation of a played move is not the orientation of its piece with its orientation id
+    OrientationMismatch {
+        piece: PieceName,
+        orientation_id: usize,
+    },
+    /// the action is not available in the current game mode
+    WrongGameMode(GameMode),
+    /// the game has ended, only [Action::Reset], [Action::Undo] and [Action::Redo] are allowed
+    GameEnded,
+    /// there are no turns to undo
+    NothingToUndo,
+    /// there are no undone turns to redo
+    NothingToRedo,
+    /// no strategy is registered under the name
+    UnknownStrategy(String),
+}
+
+/// The result of applying an [Action], as returned across the WASM boundary
+#[derive(Serialize, TS)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/ActionOutcome.ts")]
+pub enum ActionOutcome {
+    Ok(Box<GameState>),
+    Err(ActionError),
+}
+
+/// A newtype wrapper around a [Coord]
 ///
 /// Allows for defining how the type should be serialized and deserialized as well as how the TypeScript type signature should be generated
 #[derive(Serialize, Deserialize, TS)]
 #[ts(export, export_to = "pkg/types/V3.ts")]
-pub struct V3(#[ts(type = "[number, number,number]")] pub Vector3<f32>);
+pub struct V3(#[ts(type = "[number, number,number]")] pub Coord);
@@ -0,0 +1,242 @@
+//! Contains [Bitboard], a set of cells on the 8x8x8 board
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::coord::Coord;
+
+/// side length of the board in cells
+pub const SIZE: usize = 8;
+
+/// cells with z == 0 in a layer
+const Z_MIN_EDGE: u64 = 0x0101_0101_0101_0101;
+/// cells with z == 7 in a layer
+const Z_MAX_EDGE: u64 = 0x8080_8080_8080_8080;
+
+/// A set of cells on the 8x8x8 board, one bit per cell
+///
+/// Stored as one `u64` layer per height (y), the bit for a cell within a layer is `x * 8 + z`.
+/// This makes moving a whole set one cell in any direction a handful of shifts, which is used for adjacency and support checks
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Debug)]
+pub struct Bitboard([u64; SIZE]);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard([0; SIZE]);
+
+    /// the set containing every cell on the board
+    pub const FULL: Bitboard = Bitboard([u64::MAX; SIZE]);
+
+    /// the layer and bit of a coordinate, if it is on the board
+    fn bit(coord: Coord) -> Option<(usize, u64)> {
+        let on_board = |c: i32| usize::try_from(c).ok().filter(|c| *c < SIZE);
+        let (x, y, z) = (on_board(coord.x)?, on_board(coord.y)?, on_board(coord.z)?);
+        Some((y, 1 << (x * SIZE + z)))
+    }
+
+    /// the set containing a single cell, empty if the coordinate is off the board
+    pub fn from_coord(coord: Coord) -> Self {
+        let mut bitboard = Self::EMPTY;
+        bitboard.insert(coord);
+        bitboard
+    }
+
+    pub fn contains(&self, coord: Coord) -> bool {
+        Self::bit(coord).is_some_and(|(y, bit)| self.0[y] & bit != 0)
+    }
+
+    /// adds a cell to the set. Coordinates off the board are ignored
+    pub fn insert(&mut self, coord: Coord) {
+        if let Some((y, bit)) = Self::bit(coord) {
+            self.0[y] |= bit
+        }
+    }
+
+    pub fn remove(&mut self, coord: Coord) {
+        if let Some((y, bit)) = Self::bit(coord) {
+            self.0[y] &= !bit
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|layer| *layer == 0)
+    }
+
+    pub fn count(&self) -> u32 {
+        self.0.iter().map(|layer| layer.count_ones()).sum()
+    }
+
+    /// the set containing every cell at height `y`
+    pub fn layer_mask(y: usize) -> Self {
+        let mut bitboard = Self::EMPTY;
+        bitboard.0[y] = u64::MAX;
+        bitboard
+    }
+
+    /// the cells of a single layer
+    pub fn layer(&self, y: usize) -> u64 {
+        self.0[y]
+    }
+
+    /// every cell in the set moved one cell up (positive y). Cells moved off the board are dropped
+    pub fn shift_up(&self) -> Self {
+        let mut layers = [0; SIZE];
+        layers[1..].copy_from_slice(&self.0[..SIZE - 1]);
+        Self(layers)
+    }
+
+    /// every cell in the set moved one cell down (negative y). Cells moved off the board are dropped
+    pub fn shift_down(&self) -> Self {
+        let mut layers = [0; SIZE];
+        layers[..SIZE - 1].copy_from_slice(&self.0[1..]);
+        Self(layers)
+    }
+
+    /// every cell moved one cell along x, in the positive direction if `positive`
+    pub fn shift_x(&self, positive: bool) -> Self {
+        Self(
+            self.0
+                .map(|layer| if positive { layer << 8 } else { layer >> 8 }),
+        )
+    }
+
+    /// every cell moved one cell along z, in the positive direction if `positive`
+    pub fn shift_z(&self, positive: bool) -> Self {
+        Self(self.0.map(|layer| {
+            if positive {
+                (layer & !Z_MAX_EDGE) << 1
+            } else {
+                (layer & !Z_MIN_EDGE) >> 1
+            }
+        }))
+    }
+
+    /// cells sharing a face with a cell in the set, excluding the set itself
+    pub fn neighbours(&self) -> Self {
+        (self.shift_up()
+            | self.shift_down()
+            | self.shift_x(true)
+            | self.shift_x(false)
+            | self.shift_z(true)
+            | self.shift_z(false))
+            & !*self
+    }
+
+    /// cells with a cell of the set somewhere above them in the same column
+    pub fn below(&self) -> Self {
+        let mut below = Self::EMPTY;
+        let mut covering = 0;
+        for y in (0..SIZE).rev() {
+            below.0[y] = covering;
+            covering |= self.0[y];
+        }
+        below
+    }
+
+    /// iterates over the cells in the set, ordered by height, then x, then z
+    pub fn iter(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.0.iter().enumerate().flat_map(|(y, layer)| {
+            let mut remaining = *layer;
+            std::iter::from_fn(move || {
+                if remaining == 0 {
+                    return None;
+                }
+                let bit = remaining.trailing_zeros() as i32;
+                remaining &= remaining - 1;
+                Some(Coord::new(bit / SIZE as i32, y as i32, bit % SIZE as i32))
+            })
+        })
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|y| self.0[y] | rhs.0[y]))
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(std::array::from_fn(|y| self.0[y] & rhs.0[y]))
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        *self = *self & rhs
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(self.0.map(|layer| !layer))
+    }
+}
+
+impl FromIterator<Coord> for Bitboard {
+    fn from_iter<T: IntoIterator<Item = Coord>>(iter: T) -> Self {
+        let mut bitboard = Self::EMPTY;
+        for coord in iter {
+            bitboard.insert(coord)
+        }
+        bitboard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bitboard;
+    use crate::coord::Coord;
+
+    #[test]
+    fn insert_contains_iter() {
+        let coords = vec![
+            Coord::new(0, 0, 0),
+            Coord::new(7, 0, 3),
+            Coord::new(2, 5, 7),
+        ];
+        let bitboard: Bitboard = coords.iter().copied().collect();
+
+        assert!(coords.iter().all(|c| bitboard.contains(*c)));
+        assert!(!bitboard.contains(Coord::new(-1, 0, 0)));
+        assert!(!bitboard.contains(Coord::new(8, 0, 0)));
+        assert_eq!(bitboard.iter().collect::<Vec<_>>(), coords);
+        assert_eq!(bitboard.count(), 3);
+    }
+
+    #[test]
+    fn neighbours_do_not_wrap() {
+        let corner = Bitboard::from_coord(Coord::new(0, 0, 7));
+        let expected: Bitboard = [
+            Coord::new(1, 0, 7),
+            Coord::new(0, 1, 7),
+            Coord::new(0, 0, 6),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(corner.neighbours(), expected);
+
+        let edge = Bitboard::from_coord(Coord::new(3, 7, 0));
+        assert_eq!(edge.neighbours().count(), 4);
+        assert!(!edge.neighbours().contains(Coord::new(2, 7, 7)));
+    }
+
+    #[test]
+    fn below() {
+        let column = Bitboard::from_coord(Coord::new(1, 3, 2)).below();
+        assert_eq!(column.count(), 3);
+        assert!(column.contains(Coord::new(1, 0, 2)));
+        assert!(!column.contains(Coord::new(1, 3, 2)));
+    }
+}
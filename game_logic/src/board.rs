@@ -3,17 +3,40 @@
 use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use ts_rs::TS;
+use ts_rs::{Dependency, TS};
 
 use crate::{
-    game_mode::{GameMode, TwoPlayerMap},
+    bitboard::{Bitboard, SIZE},
+    coord::{self, Coord},
+    game_mode::{FreeForAllMap, GameMode, SolitaireMap, TwoPlayerMap},
     player::{self, Player},
+    rules::{Rules, ScoringMethod, SupportRule, TouchRule},
+    zobrist,
 };
 
 /// Represents the state of the board
-#[derive(Serialize, Deserialize, Debug, TS, Clone)]
-#[ts(export, export_to = "pkg/types/Board.ts")]
+///
+/// Cells are stored as [Bitboard]s, one for the cells in bounds and one per player for the cells they occupy.
+/// Serialized as [SerializedBoard], the cell array consumed by the web app
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "SerializedBoard", into = "SerializedBoard")]
 pub struct Board {
+    /// cells that are part of the map
+    in_bounds: Bitboard,
+    /// cells occupied by each player, indexed by [Player::index]
+    occupied: [Bitboard; 4],
+    /// used to construct, show available space to player
+    pub height_limits: Vec<Vec<usize>>,
+    /// useful for centering a camera
+    pub center: Vector3<f32>,
+    /// Zobrist hash of the occupied cells, kept up to date as cubes are added and removed
+    hash: u64,
+}
+
+/// The serialized form of [Board]
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, rename = "Board", export_to = "pkg/types/Board.ts")]
+pub struct SerializedBoard {
     pub cells: [[[BoardCell; 8]; 8]; 8],
     /// used to construct, show available space to player
     #[ts(type = "Array<Array<number>>")]
@@ -23,11 +46,73 @@ pub struct Board {
     pub center: Vector3<f32>,
 }
 
-fn v3_to_index(v3: Vector3<f32>) -> Option<(usize, usize, usize)> {
-    if v3.x >= 0.0 && v3.y >= 0.0 && v3.z >= 0.0 {
-        Some((v3.x as usize, v3.y as usize, v3.z as usize))
-    } else {
-        None
+impl From<SerializedBoard> for Board {
+    fn from(serialized: SerializedBoard) -> Self {
+        let mut board = Board {
+            in_bounds: Bitboard::EMPTY,
+            occupied: [Bitboard::EMPTY; 4],
+            height_limits: serialized.height_limits,
+            center: serialized.center,
+            hash: 0,
+        };
+
+        for (x, layers) in serialized.cells.iter().enumerate() {
+            for (y, row) in layers.iter().enumerate() {
+                for (z, cell) in row.iter().enumerate() {
+                    let coord = Coord::new(x as i32, y as i32, z as i32);
+                    match cell {
+                        BoardCell::Empty => board.in_bounds.insert(coord),
+                        BoardCell::Player(player) => {
+                            board.in_bounds.insert(coord);
+                            board.occupied[player.index()].insert(coord);
+                        }
+                        BoardCell::OutOfBounds => {}
+                    }
+                }
+            }
+        }
+
+        board.hash = board.recompute_hash();
+        board
+    }
+}
+
+impl From<Board> for SerializedBoard {
+    fn from(board: Board) -> Self {
+        Self {
+            cells: std::array::from_fn(|x| {
+                std::array::from_fn(|y| {
+                    std::array::from_fn(|z| board.cell(Coord::new(x as i32, y as i32, z as i32)))
+                })
+            }),
+            height_limits: board.height_limits,
+            center: board.center,
+        }
+    }
+}
+
+/// [Board] is exported to TypeScript as its serialized form
+impl TS for Board {
+    const EXPORT_TO: Option<&'static str> = SerializedBoard::EXPORT_TO;
+
+    fn decl() -> String {
+        SerializedBoard::decl()
+    }
+
+    fn name() -> String {
+        SerializedBoard::name()
+    }
+
+    fn inline() -> String {
+        SerializedBoard::inline()
+    }
+
+    fn dependencies() -> Vec<Dependency> {
+        SerializedBoard::dependencies()
+    }
+
+    fn transparent() -> bool {
+        SerializedBoard::transparent()
     }
 }
 
@@ -69,192 +154,272 @@ impl Board {
         }
     }
 
+    fn solitaire_map_to_heights(map: SolitaireMap) -> Vec<Vec<usize>> {
+        match map {
+            SolitaireMap::FourByFiveByTwo => vec![vec![2; 5]; 4],
+        }
+    }
+
+    fn free_for_all_map_to_heights(map: FreeForAllMap) -> Vec<Vec<usize>> {
+        match map {
+            FreeForAllMap::Plaza => vec![vec![3; 8]; 8],
+            FreeForAllMap::Ziggurat => vec![
+                vec![2, 2, 2, 2, 2, 2, 2, 2],
+                vec![2, 3, 3, 3, 3, 3, 3, 2],
+                vec![2, 3, 4, 4, 4, 4, 3, 2],
+                vec![2, 3, 4, 5, 5, 4, 3, 2],
+                vec![2, 3, 4, 5, 5, 4, 3, 2],
+                vec![2, 3, 4, 4, 4, 4, 3, 2],
+                vec![2, 3, 3, 3, 3, 3, 3, 2],
+                vec![2, 2, 2, 2, 2, 2, 2, 2],
+            ],
+        }
+    }
+
     pub fn new(game_mode: GameMode) -> Self {
         Self::new_board_from_2d_heights(match game_mode {
-            GameMode::Solitaire(_) => todo!(),
+            GameMode::Solitaire(map) => Self::solitaire_map_to_heights(map),
             GameMode::TwoPlayer(map) => Self::map_to_heights(map),
-            GameMode::VSGreedyAI(map) => Self::map_to_heights(map),
+            GameMode::VSGreedyAI(opponent) => Self::map_to_heights(opponent.map),
+            GameMode::FreeForAll { map, .. } => Self::free_for_all_map_to_heights(map),
         })
     }
 
-    pub fn calculate_score(&self) -> HashMap<Player, i8> {
-        let highests: Vec<Player> = self
-            .height_limits
-            .iter()
-            .enumerate()
-            .flat_map(|(x, row)| {
-                row.iter()
-                    .enumerate()
-                    .filter_map(|(z, y_max)| {
-                        self.get_highest_player(x as i8, z as i8, *y_max as i8)
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect();
-
-        let total = highests.len();
+    pub fn calculate_score(&self, rules: &Rules) -> HashMap<Player, i8> {
+        Self::score_occupied(rules, &self.occupied)
+    }
 
-        let p1s = highests
-            .into_iter()
-            .filter(|item| item == &Player::P1)
-            .count();
+    /// The score if a player placed cubes at the given positions, without changing the board
+    pub fn calculate_score_with(
+        &self,
+        rules: &Rules,
+        player: Player,
+        cubes: impl IntoIterator<Item = Coord>,
+    ) -> HashMap<Player, i8> {
+        let mut occupied = self.occupied;
+        occupied[player.index()] |= cubes.into_iter().collect::<Bitboard>() & self.in_bounds;
+        Self::score_occupied(rules, &occupied)
+    }
 
-        HashMap::from([(Player::P1, p1s as i8), (Player::P2, (total - p1s) as i8)])
+    fn score_occupied(rules: &Rules, occupied: &[Bitboard; 4]) -> HashMap<Player, i8> {
+        let all_occupied = occupied
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, occupied| acc | *occupied);
+        let scoring_cells = match rules.scoring {
+            // a cell is the highest in its column if no occupied cell is above it
+            ScoringMethod::ColumnTops => all_occupied & !all_occupied.below(),
+            ScoringMethod::CubeCount => Bitboard::FULL,
+        };
+
+        HashMap::from(Player::ALL.map(|player| {
+            (
+                player,
+                (scoring_cells & occupied[player.index()]).count() as i8,
+            )
+        }))
     }
 
     /// returns all available positions on the board. Used for searching for possible moves
-    pub fn get_available_positions(&self) -> Vec<Vector3<f32>> {
-        self.height_limits
-            .iter()
-            .enumerate()
-            .flat_map(|(x, row)| {
-                row.iter()
-                    .enumerate()
-                    .flat_map(|(z, y_max)| {
-                        (0..=*y_max).filter_map(move |y| match self.get_from_index((x, y, z)) {
-                            Some(&BoardCell::Empty) => {
-                                Some(Vector3::new(x as f32, y as f32, z as f32))
-                            }
-                            _ => None,
-                        })
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .collect()
+    pub fn get_available_positions(&self) -> Vec<Coord> {
+        self.empty_cells().iter().collect()
+    }
+
+    /// cells that are part of the map
+    pub fn in_bounds(&self) -> Bitboard {
+        self.in_bounds
+    }
+
+    /// cells in bounds that are not occupied
+    pub fn empty_cells(&self) -> Bitboard {
+        self.in_bounds & !self.all_occupied()
     }
 
-    /// Returns the highest player for a given column, used for scoring
-    fn get_highest_player(&self, x: i8, z: i8, y_max: i8) -> Option<Player> {
-        // for each pair (x,z) starting from height y and working downwards, find the first board cell that is owned by a player
-        (0..y_max).rev().find_map(
-            |y| match self.get(Vector3::new(x as f32, y as f32, z as f32)) {
-                Some(&BoardCell::Player(player)) => Some(player),
-                _ => None,
-            },
-        )
+    /// the occupied cells with no cube above them
+    pub fn column_tops(&self) -> Bitboard {
+        let all_occupied = self.all_occupied();
+        all_occupied & !all_occupied.below()
     }
 
-    fn get_from_index(&self, (x, y, z): (usize, usize, usize)) -> Option<&BoardCell> {
-        self.cells
-            .get(x)
-            .and_then(|b| b.get(y))
-            .and_then(|c| c.get(z))
+    /// cells occupied by any player
+    pub(crate) fn all_occupied(&self) -> Bitboard {
+        self.occupied
+            .iter()
+            .fold(Bitboard::EMPTY, |acc, occupied| acc | *occupied)
     }
 
-    fn get_from_index_mut(&mut self, (x, y, z): (usize, usize, usize)) -> Option<&mut BoardCell> {
-        self.cells
-            .get_mut(x)
-            .and_then(|b| b.get_mut(y))
-            .and_then(|c| c.get_mut(z))
+    /// cells occupied by a player
+    pub fn occupied_by(&self, player: Player) -> Bitboard {
+        self.occupied[player.index()]
     }
 
-    pub fn get(&self, index: Vector3<f32>) -> Option<&BoardCell> {
-        if let Some((x, y, z)) = v3_to_index(index) {
-            self.get_from_index((x, y, z))
+    /// the contents of a cell, cells off the board are out of bounds
+    fn cell(&self, index: Coord) -> BoardCell {
+        if !self.in_bounds.contains(index) {
+            BoardCell::OutOfBounds
+        } else if let Some(player) = Player::ALL
+            .into_iter()
+            .find(|player| self.occupied[player.index()].contains(index))
+        {
+            BoardCell::Player(player)
         } else {
-            None
+            BoardCell::Empty
         }
     }
 
-    pub fn get_mut(&mut self, index: Vector3<f32>) -> Option<&mut BoardCell> {
-        if let Some((x, y, z)) = v3_to_index(index) {
-            self.get_from_index_mut((x, y, z))
-        } else {
-            None
+    /// the contents of a cell, if the cell is within the 8x8x8 board
+    pub fn get(&self, index: Coord) -> Option<BoardCell> {
+        coord::to_index(index)
+            .filter(|(x, y, z)| *x < SIZE && *y < SIZE && *z < SIZE)
+            .map(|_| self.cell(index))
+    }
+
+    pub fn add_cubes(&mut self, cubes: &[Cube]) {
+        for cube in cubes {
+            let occupied = &mut self.occupied[cube.player.index()];
+            if self.in_bounds.contains(cube.position) && !occupied.contains(cube.position) {
+                occupied.insert(cube.position);
+                self.hash ^= zobrist::cell(cube.player, cube.position);
+            }
         }
     }
 
-    pub fn add_cubes(&mut self, cubes: &Vec<Cube>) {
+    /// removes cubes from the board, used when undoing a turn
+    pub fn remove_cubes(&mut self, cubes: &[Cube]) {
         for cube in cubes {
-            if let Some(bc) = self.get_mut(cube.position) {
-                *bc = BoardCell::Player(cube.player)
+            for player in Player::ALL {
+                let occupied = &mut self.occupied[player.index()];
+                if occupied.contains(cube.position) {
+                    occupied.remove(cube.position);
+                    self.hash ^= zobrist::cell(player, cube.position);
+                }
             }
         }
     }
 
-    pub fn supports(&self, position: &Vector3<f32>) -> bool {
-        let supported_by_piece = self
-            .get(*position - Vector3::<f32>::new(0.0, 1.0, 0.0))
-            .is_some_and(|bc| *bc != BoardCell::Empty);
+    /// Zobrist hash of the occupied cells
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// hashes the occupied cells from scratch
+    pub fn recompute_hash(&self) -> u64 {
+        Player::ALL
+            .into_iter()
+            .flat_map(|player| {
+                self.occupied[player.index()]
+                    .iter()
+                    .map(move |cell| zobrist::cell(player, cell))
+            })
+            .fold(0, |hash, key| hash ^ key)
+    }
 
-        let is_on_ground = position.y == 0.0;
+    pub fn supports(&self, position: &Coord) -> bool {
+        let supported_by_piece = self.all_occupied().contains(position - Coord::y());
+
+        let is_on_ground = position.y == 0;
 
         supported_by_piece || is_on_ground
     }
 
-    fn get_adjacent_cells(&self, position: &Vector3<f32>) -> Vec<&BoardCell> {
-        let positions = vec![
-            position - Vector3::<f32>::new(1.0, 0.0, 0.0),
-            position - Vector3::<f32>::new(-1.0, 0.0, 0.0),
-            position - Vector3::<f32>::new(0.0, 1.0, 0.0),
-            position - Vector3::<f32>::new(0.0, -1.0, 0.0),
-            position - Vector3::<f32>::new(0.0, 0.0, 1.0),
-            position - Vector3::<f32>::new(0.0, 0.0, -1.0),
-        ];
+    /// Whether a player may place cubes at the given positions
+    ///
+    /// Agrees with [BoardState::check_piece_placement](crate::board_state::BoardState::check_piece_placement) reporting no errors, without building the preview
+    pub fn is_legal_placement(
+        &self,
+        rules: &Rules,
+        player: Player,
+        cubes: impl IntoIterator<Item = Coord>,
+    ) -> bool {
+        let mut cube_count = 0;
+        let piece: Bitboard = cubes.into_iter().inspect(|_| cube_count += 1).collect();
+
+        // every cube is on the board, in bounds and not colliding
+        piece.count() == cube_count
+            && (piece & !self.empty_cells()).is_empty()
+            && self.unsupported(rules, piece).is_empty()
+            && self.touches_required_piece(rules, player, piece)
+    }
 
-        positions.iter().filter_map(|p| self.get(*p)).collect()
+    /// cubes of a piece that are neither on the ground nor above a cube that may support them
+    fn unsupported(&self, rules: &Rules, piece: Bitboard) -> Bitboard {
+        let ground = Bitboard::layer_mask(0);
+        let supporting = match rules.support {
+            SupportRule::PieceSupport => self.all_occupied() | piece,
+            SupportRule::Gravity => self.all_occupied(),
+        };
+        piece & !(ground | supporting.shift_up())
     }
 
-    /// implements game rule requiring players to play touching their own piece.
+    /// whether a piece touches the cells the player is required to touch
+    fn touches_required_piece(&self, rules: &Rules, player: Player, piece: Bitboard) -> bool {
+        match self.cells_to_touch(rules, player) {
+            Some(cells_to_touch) => !(piece.neighbours() & cells_to_touch).is_empty(),
+            None => true,
+        }
+    }
+
+    /// The cells a player's next piece must touch, if any
     ///
-    /// The first player may play anywhere. The second must touch the first
-    pub fn check_touches_piece(&self, new_piece_coords: Vec<Cube>) -> Result<Vec<Cube>, Vec<Cube>> {
-        let player = new_piece_coords[0].player;
+    /// The first player may play anywhere, after this the [TouchRule] decides
+    fn cells_to_touch(&self, rules: &Rules, player: Player) -> Option<Bitboard> {
+        match rules.touch {
+            TouchRule::TouchOwn if self.player_has_played(player).is_some() => {
+                Some(self.occupied[player.index()])
+            }
+            TouchRule::TouchOwn | TouchRule::TouchAny => {
+                Some(self.all_occupied()).filter(|occupied| !occupied.is_empty())
+            }
+        }
+    }
 
-        // the first player can play anywhere, the second player mst touch the first, after this, all players must touch their own piece
-        let player_to_check_for = self
-            .player_has_played(player)
-            .or(self.player_has_played(player.get_other()));
+    /// implements game rule requiring players to play touching a piece, as set by the [TouchRule]
+    ///
+    /// The first player may play anywhere. By default each player's first piece must touch a piece already in play, after this, all players must touch their own piece
+    pub fn check_touches_piece(
+        &self,
+        rules: &Rules,
+        new_piece_coords: Vec<Cube>,
+    ) -> Result<Vec<Cube>, Vec<Cube>> {
+        let player = new_piece_coords[0].player;
+        let new_piece: Bitboard = new_piece_coords.iter().map(|c| c.position).collect();
 
-        if let Some(player) = player_to_check_for {
-            if new_piece_coords
-                .iter()
-                .flat_map(|p| self.get_adjacent_cells(&p.position))
-                .any(|bc| *bc == BoardCell::Player(player))
-            {
-                Ok(new_piece_coords)
-            } else {
-                //embed error in all cubes
-                Err(new_piece_coords
-                    .iter()
-                    .map(|cube| Cube {
-                        error: Some(CubeError::NotTouchingPiece),
-                        ..*cube
-                    })
-                    .collect())
-            }
-        } else {
-            // neither player has played, free to play anywhere
+        if self.touches_required_piece(rules, player, new_piece) {
             Ok(new_piece_coords)
+        } else {
+            //embed error in all cubes
+            Err(new_piece_coords
+                .iter()
+                .map(|cube| Cube {
+                    error: Some(CubeError::NotTouchingPiece),
+                    ..*cube
+                })
+                .collect())
         }
     }
 
     /// determines whether or not a player has played
     fn player_has_played(&self, player: Player) -> Option<Player> {
-        self.cells
-            .into_iter()
-            .flat_map(|a| a.into_iter().flat_map(|b| b.into_iter()))
-            .find(|bc| bc == &BoardCell::Player(player))
-            .and(Some(player))
+        (!self.occupied[player.index()].is_empty()).then_some(player)
     }
 
-    pub fn check_in_bounds_no_collision(&self, position: Vector3<f32>) -> Option<CubeError> {
-        match self.get(position) {
-            Some(BoardCell::Empty) => None,
-            Some(BoardCell::Player(_)) => Some(CubeError::Collision),
-            _ => Some(CubeError::OutOfBounds),
+    pub fn check_in_bounds_no_collision(&self, position: Coord) -> Option<CubeError> {
+        match self.cell(position) {
+            BoardCell::Empty => None,
+            BoardCell::Player(_) => Some(CubeError::Collision),
+            BoardCell::OutOfBounds => Some(CubeError::OutOfBounds),
         }
     }
 
     fn new_board_from_2d_heights(heights_2d: Vec<Vec<usize>>) -> Self {
-        let mut board = [[[BoardCell::OutOfBounds; 8]; 8]; 8];
-        for (ix, x) in heights_2d.iter().enumerate() {
-            for (iz, ymax) in x.iter().enumerate() {
-                for y in 0..*ymax {
-                    board[ix][y][iz] = BoardCell::Empty
-                }
-            }
-        }
+        let in_bounds = heights_2d
+            .iter()
+            .enumerate()
+            .flat_map(|(x, row)| {
+                row.iter().enumerate().flat_map(move |(z, y_max)| {
+                    (0..*y_max).map(move |y| Coord::new(x as i32, y as i32, z as i32))
+                })
+            })
+            .collect();
 
         let mid_x = (heights_2d.len() as f32 - 1.0) / 2.0;
 
@@ -275,9 +440,11 @@ impl Board {
             / 2.0;
 
         Self {
-            cells: board,
+            in_bounds,
+            occupied: [Bitboard::EMPTY; 4],
             height_limits: heights_2d,
             center: Vector3::<f32>::new(mid_x, mid_y, mid_z),
+            hash: 0,
         }
     }
 }
@@ -290,9 +457,10 @@ impl Default for Board {
 
 #[cfg(test)]
 mod test {
-    use nalgebra::Vector3;
-
-    use crate::{action::V3, board_state::BoardState, game_state::GameState, player::Player};
+    use crate::{
+        action::V3, board::Board, board_state::BoardState, coord::Coord, game_state::GameState,
+        player::Player, rules::Rules,
+    };
 
     #[test]
     fn print() {
@@ -300,13 +468,14 @@ mod test {
 
         gs.apply_action(crate::action::Action::SelectPiece(
             crate::piece::PieceName::Corner,
-        ));
+        ))
+        .unwrap();
 
-        gs.apply_action(crate::action::Action::PreviewPiece(V3(
-            Vector3::<f32>::new(0.0, 0.0, 0.0),
-        )));
+        gs.apply_action(crate::action::Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
 
-        gs.apply_action(crate::action::Action::PlayPreviewedPiece);
+        gs.apply_action(crate::action::Action::PlayPreviewedPiece)
+            .unwrap();
 
         // assert!(gs.score[&Player::P1] == 3);
         // assert!(gs.score[&Player::P2] == 0);
@@ -314,13 +483,38 @@ mod test {
         println!("{}", serde_json::to_string(&gs).unwrap())
     }
 
+    #[test]
+    fn serialization_round_trip() {
+        let mut gs = GameState::new(crate::game_mode::GameMode::TwoPlayer(
+            crate::game_mode::TwoPlayerMap::Wall,
+        ));
+        let moves = gs.legal_moves(Player::P1);
+        gs.play_move(&moves[0]).unwrap();
+
+        let board = &gs.board_state.board;
+        let value = serde_json::to_value(board).unwrap();
+        assert_eq!(value["cells"].as_array().unwrap().len(), 8);
+        assert_eq!(
+            value["cells"][7][2][0],
+            serde_json::json!({"type": "OutOfBounds"})
+        );
+
+        let round_tripped: Board = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&round_tripped).unwrap(), value);
+        assert_eq!(
+            round_tripped.occupied_by(Player::P1),
+            board.occupied_by(Player::P1)
+        );
+        assert_eq!(round_tripped.empty_cells(), board.empty_cells());
+    }
+
     #[test]
     fn score() {
         let b = BoardState::new(crate::game_mode::GameMode::TwoPlayer(
             crate::game_mode::TwoPlayerMap::Stairs,
         ));
 
-        let score = b.calculate_score();
+        let score = b.calculate_score(&Rules::default());
         println!("{:?}", score);
         assert!(score.contains_key(&Player::P1));
         assert!(score.contains_key(&Player::P2));
@@ -351,6 +545,6 @@ pub enum CubeError {
 pub struct Cube {
     pub player: player::Player,
     #[ts(type = "[number,number,number]")]
-    pub position: Vector3<f32>,
+    pub position: Coord,
     pub error: Option<CubeError>,
 }
@@ -1,15 +1,17 @@
 //! Contains [BoardState]
 
-use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use ts_rs::TS;
 
 use crate::{
+    action::ActionError,
     board::{Board, Cube, CubeError},
+    coord::Coord,
     game_mode::GameMode,
     piece::Piece,
     player::Player,
+    rules::{Rules, SupportRule},
 };
 /// The state of the board, including move preview
 #[derive(Serialize, Deserialize, Default, Debug, TS, Clone)]
@@ -34,9 +36,15 @@ impl BoardState {
     /// Used for allowing a user to visualize the effect of placing a piece on the board
     ///
     /// Contains information about move validity
-    pub fn preview_piece(&mut self, current_player: Player, piece: Piece, position: Vector3<f32>) {
+    pub fn preview_piece(
+        &mut self,
+        rules: &Rules,
+        current_player: Player,
+        piece: Piece,
+        position: Coord,
+    ) {
         self.previewed_piece =
-            Some(self.check_piece_placement(current_player, piece.clone(), position));
+            Some(self.check_piece_placement(rules, current_player, piece, position));
     }
 
     ///  performs in bounds and collision checks
@@ -51,13 +59,16 @@ impl BoardState {
         }
     }
 
-    /// Checks that a given cube of the potential play is supported either by a piece already in play or by another cube in the same piece
+    /// Checks that a given cube of the potential play is supported either by a piece already in play or, unless playing with [SupportRule::Gravity], by another cube in the same piece
     ///
     /// adds CubeError::Unsupported if not
-    fn check_cube_supported(&self, preview_cube: Cube, moved_piece: &Piece) -> Cube {
-        if self.board.supports(&preview_cube.position)
-            || moved_piece.supports(&preview_cube.position)
-        {
+    fn check_cube_supported(&self, rules: &Rules, preview_cube: Cube, moved_piece: &Piece) -> Cube {
+        let supported_by_piece = match rules.support {
+            SupportRule::PieceSupport => moved_piece.supports(&preview_cube.position),
+            SupportRule::Gravity => false,
+        };
+
+        if self.board.supports(&preview_cube.position) || supported_by_piece {
             preview_cube
         } else {
             Cube {
@@ -70,9 +81,10 @@ impl BoardState {
     /// returns Vec of cubes with position and possible error information
     pub fn check_piece_placement(
         &self,
+        rules: &Rules,
         current_player: Player,
         piece: Piece,
-        position: Vector3<f32>,
+        position: Coord,
     ) -> Vec<Cube> {
         // build the piece from the piece and position offset
         let moved_piece = piece.get_moved_copy(position);
@@ -87,28 +99,33 @@ impl BoardState {
             })
             .collect();
 
-        match self.board.check_touches_piece(preview_cubes) {
+        match self.board.check_touches_piece(rules, preview_cubes) {
             Ok(cubes) => cubes
                 .iter()
                 .map(|preview_cube| Self::check_in_bounds_no_collision(self, preview_cube))
-                .map(|preview_cube| Self::check_cube_supported(self, preview_cube, &moved_piece))
+                .map(|preview_cube| {
+                    Self::check_cube_supported(self, rules, preview_cube, &moved_piece)
+                })
                 .collect(),
             Err(cubes) => cubes,
         }
     }
 
     /// Plays the selected piece if the previewed move is valid
-    pub fn play_selected_piece(&mut self) -> Result<(), ()> {
-        if let Some(preview_cubes) = &self.previewed_piece {
-            if preview_cubes.iter().all(|c| c.error.is_none()) {
-                self.board.add_cubes(preview_cubes);
-                self.previewed_piece = None;
-                Ok(())
-            } else {
-                Err(())
-            }
+    pub fn play_selected_piece(&mut self) -> Result<(), ActionError> {
+        let preview_cubes = self
+            .previewed_piece
+            .as_ref()
+            .ok_or(ActionError::NoPiecePreviewed)?;
+
+        let errors: Vec<CubeError> = preview_cubes.iter().filter_map(|c| c.error).collect();
+
+        if errors.is_empty() {
+            self.board.add_cubes(preview_cubes);
+            self.previewed_piece = None;
+            Ok(())
         } else {
-            Err(())
+            Err(ActionError::InvalidPreview(errors))
         }
     }
 
@@ -118,8 +135,8 @@ impl BoardState {
     }
 
     /// Returns the current score
-    pub fn calculate_score(&self) -> HashMap<Player, i8> {
-        self.board.calculate_score()
+    pub fn calculate_score(&self, rules: &Rules) -> HashMap<Player, i8> {
+        self.board.calculate_score(rules)
     }
 }
 
@@ -128,17 +145,19 @@ mod tests {
     use crate::{
         action::{Action, V3},
         board::CubeError,
+        coord::Coord,
         game_state::GameState,
         piece::PieceName,
     };
-    use nalgebra::Vector3;
 
     #[test]
     fn test_supported_y_0() {
         let mut gs = GameState::default();
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
 
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 0.0, 0.0))));
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
 
         if let Some(preview_piece) = gs.board_state.previewed_piece {
             assert!(preview_piece.iter().all(|c| c.error.is_none()));
@@ -148,19 +167,23 @@ mod tests {
     #[test]
     fn test_supported_other_piece() {
         let mut gs = GameState::default();
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
 
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 0.0, 0.0))));
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
 
         if let Some(preview_piece) = &gs.board_state.previewed_piece {
             assert!(preview_piece.iter().all(|c| c.error.is_none()));
         }
 
-        gs.apply_action(Action::PlayPreviewedPiece);
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
 
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
 
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 1.0, 0.0))));
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 1, 0))))
+            .unwrap();
 
         if let Some(a) = &gs.board_state.previewed_piece {
             assert!(a.iter().all(|c| c.error.is_none()));
@@ -170,9 +193,11 @@ mod tests {
     #[test]
     fn test_supported_unsupported() {
         let mut gs = GameState::default();
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
 
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 1.0, 0.0))));
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 1, 0))))
+            .unwrap();
 
         if let Some(preview_piece) = &gs.board_state.previewed_piece {
             assert!(preview_piece
@@ -187,13 +212,17 @@ mod tests {
         let mut gs = GameState::default();
 
         // place piece
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 0.0, 0.0))));
-        gs.apply_action(Action::PlayPreviewedPiece);
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
 
         //attempts to place another piece at the same position
-        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo));
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 0.0, 0.0))));
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
 
         if let Some(preivew_cubes) = &gs.board_state.previewed_piece {
             println!("{:?}", preivew_cubes);
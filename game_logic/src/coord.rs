@@ -0,0 +1,75 @@
+//! Contains [Coord] and [Rotation], the integer lattice types used to address board cells
+
+use nalgebra::{Matrix3, Vector3};
+
+/// A position on the integer lattice of board cells, or an offset between two cells
+pub type Coord = Vector3<i32>;
+
+/// An exact rotation of the lattice, a product of 90° rotations about the coordinate axes
+pub type Rotation = Matrix3<i32>;
+
+/// 90° rotation about the x axis in the positive direction
+pub fn quarter_turn_x() -> Rotation {
+    Rotation::new(1, 0, 0, 0, 0, -1, 0, 1, 0)
+}
+
+/// 90° rotation about the y axis in the positive direction
+pub fn quarter_turn_y() -> Rotation {
+    Rotation::new(0, 0, 1, 0, 1, 0, -1, 0, 0)
+}
+
+/// 90° rotation about the z axis in the positive direction
+pub fn quarter_turn_z() -> Rotation {
+    Rotation::new(0, -1, 0, 1, 0, 0, 0, 0, 1)
+}
+
+/// applies a rotation `turns` times
+pub fn repeat(rotation: Rotation, turns: u32) -> Rotation {
+    (0..turns).fold(Rotation::identity(), |acc, _| rotation * acc)
+}
+
+/// Converts a coordinate to indices into the board, if every component is non-negative
+pub fn to_index(coord: Coord) -> Option<(usize, usize, usize)> {
+    Some((
+        usize::try_from(coord.x).ok()?,
+        usize::try_from(coord.y).ok()?,
+        usize::try_from(coord.z).ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use nalgebra::{Rotation3, Vector3};
+
+    use super::{quarter_turn_x, quarter_turn_y, quarter_turn_z, repeat, Coord, Rotation};
+
+    /// the integer rotations agree with the floating point rotations they replace
+    #[test]
+    fn matches_float_rotations() {
+        let axes = [
+            (quarter_turn_x(), Vector3::x_axis()),
+            (quarter_turn_y(), Vector3::y_axis()),
+            (quarter_turn_z(), Vector3::z_axis()),
+        ];
+        let coord = Coord::new(1, 2, 3);
+
+        for (rotation, axis) in axes {
+            let expected = Rotation3::from_axis_angle(&axis, PI / 2.0)
+                * Vector3::new(coord.x as f32, coord.y as f32, coord.z as f32);
+            let actual = rotation * coord;
+            assert_eq!(
+                Vector3::new(actual.x as f32, actual.y as f32, actual.z as f32),
+                expected.map(f32::round)
+            );
+        }
+    }
+
+    #[test]
+    fn four_turns_is_identity() {
+        for rotation in [quarter_turn_x(), quarter_turn_y(), quarter_turn_z()] {
+            assert_eq!(repeat(rotation, 4), Rotation::identity());
+        }
+    }
+}
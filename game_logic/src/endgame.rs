@@ -0,0 +1,334 @@
+//! Contains [solve], an exact search of the game tree to the end of the game, and [annotate], which checks the endgame of a played game against it
+//!
+//! Only small endgames can be solved, [is_solvable] tells whether a position has few enough legal moves left.
+//! Games with more than two players are solved paranoid, as in [search](crate::search)
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    action::Action,
+    game_move::Move,
+    game_state::{legal_moves_on, score_margin, GameState},
+    history::Turn,
+    player::Player,
+    zobrist::{Bound, Entry, TranspositionTable},
+};
+
+/// Positions with at most this many legal moves left, summed over every player, are solved by default
+pub const DEFAULT_THRESHOLD: usize = 16;
+
+/// A solved position
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/Solution.ts")]
+pub struct Solution {
+    /// the final score margin of the solving player under perfect play
+    pub margin: i8,
+    /// the moves played from the position to the end of the game under perfect play, with the player making each
+    pub line: Vec<(Player, Move)>,
+    /// positions visited
+    #[ts(type = "number")]
+    pub nodes: u64,
+}
+
+/// The number of legal moves left in a position, summed over every player
+///
+/// Counts no further than `limit`
+pub fn remaining_moves(state: &GameState, limit: usize) -> usize {
+    state
+        .player_state
+        .players_in_turn_order()
+        .into_iter()
+        .flat_map(|player| {
+            legal_moves_on(
+                &state.board_state.board,
+                &state.rules,
+                state.player_state.players.get(&player),
+                player,
+            )
+        })
+        .take(limit)
+        .count()
+}
+
+/// Whether the game is still being played and has at most `threshold` legal moves left
+pub fn is_solvable(state: &GameState, threshold: usize) -> bool {
+    !state.game_ended && remaining_moves(state, threshold + 1) <= threshold
+}
+
+/// Solves the position for the current player
+pub fn solve(state: &GameState) -> Solution {
+    solve_for(state, state.player_state.current_player)
+}
+
+/// Solves the position for a player, who may not be the player to move
+///
+/// Searches to the end of the game regardless of its size, check [is_solvable] first
+pub fn solve_for(state: &GameState, player: Player) -> Solution {
+    let mut solver = Solver {
+        player,
+        table: TranspositionTable::new(1 << 14),
+        nodes: 0,
+    };
+    let margin = solver.search(state, i8::MIN, i8::MAX);
+
+    // follows a move preserving the proven margin at every position
+    let mut line = vec![];
+    let mut position = state.clone();
+    while !position.game_ended {
+        let mover = position.player_state.current_player;
+        let Some((game_move, next)) = solver
+            .children(&position)
+            .into_iter()
+            .find(|(_, next)| solver.search(next, i8::MIN, i8::MAX) == margin)
+        else {
+            break;
+        };
+        line.push((mover, game_move));
+        position = next;
+    }
+
+    Solution {
+        margin,
+        line,
+        nodes: solver.nodes,
+    }
+}
+
+/// Entries of solved positions are marked with the greatest depth, they hold for any search
+const SOLVED: u8 = u8::MAX;
+
+struct Solver {
+    /// the player the margin is measured for
+    player: Player,
+    table: TranspositionTable,
+    nodes: u64,
+}
+
+impl Solver {
+    /// the final margin of the solving player under perfect play, exact if it lies between alpha and beta
+    fn search(&mut self, state: &GameState, mut alpha: i8, mut beta: i8) -> i8 {
+        self.nodes += 1;
+        if state.game_ended {
+            return score_margin(&state.score, self.player);
+        }
+
+        let hash = state.hash();
+        let (alpha_original, beta_original) = (alpha, beta);
+        if let Some(entry) = self.table.get(hash) {
+            let value = entry.value as i8;
+            match entry.bound {
+                Bound::Exact => return value,
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+            if alpha >= beta {
+                return value;
+            }
+        }
+
+        let children = self.children(state);
+        if children.is_empty() {
+            return score_margin(&state.score, self.player);
+        }
+
+        let maximizing = state.player_state.current_player == self.player;
+        let mut best = if maximizing { i8::MIN } else { i8::MAX };
+        for (_, next) in children {
+            let value = self.search(&next, alpha, beta);
+            if maximizing {
+                best = best.max(value);
+                alpha = alpha.max(value);
+            } else {
+                best = best.min(value);
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        self.table.insert(Entry {
+            hash,
+            depth: SOLVED,
+            value: best as f32,
+            bound: if best <= alpha_original {
+                Bound::Upper
+            } else if best >= beta_original {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            },
+            best_move: None,
+        });
+
+        best
+    }
+
+    /// the positions after each legal move of the player to move, the best scoring for that player first
+    ///
+    /// players without a legal move are skipped by the game, so the player to move always has one until the game ends
+    fn children(&self, state: &GameState) -> Vec<(Move, GameState)> {
+        let mover = state.player_state.current_player;
+        let mut children: Vec<(i8, Move, GameState)> = state
+            .legal_moves(mover)
+            .into_iter()
+            .map(|game_move| {
+                let mut next = state.clone();
+                next.play_move(&game_move)
+                    .expect("legal moves can be played");
+                (next.score_margin(mover), game_move, next)
+            })
+            .collect();
+        children.sort_by_key(|(margin, _, _)| std::cmp::Reverse(*margin));
+
+        children
+            .into_iter()
+            .map(|(_, game_move, next)| (game_move, next))
+            .collect()
+    }
+}
+
+/// How a turn of a played game compares to perfect play
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/Annotation.ts")]
+pub struct Annotation {
+    /// index of the turn in the game's history
+    pub turn: usize,
+    pub player: Player,
+    /// the final margin the player could have achieved under perfect play
+    pub best_margin: i8,
+    /// the final margin after the turn played, under perfect play from then on
+    pub played_margin: i8,
+    /// a move achieving the best margin
+    pub best_move: Option<Move>,
+}
+
+impl Annotation {
+    /// whether the turn played gave away part of the best margin
+    pub fn is_mistake(&self) -> bool {
+        self.played_margin < self.best_margin
+    }
+}
+
+/// Replays a game's history and annotates every turn taken in a position with at most `threshold` legal moves left
+pub fn annotate(game: &GameState, threshold: usize) -> Vec<Annotation> {
+    let mut state = GameState::with_rules(game.game_mode, game.rules).with_seed(game.seed);
+    let mut annotations = vec![];
+
+    for (turn, entry) in game.history.turns().iter().enumerate() {
+        let before = state.clone();
+        let played = match &entry.turn {
+            Turn::Played { cubes, .. } => {
+                let placed: Vec<_> = cubes.iter().map(|cube| cube.position).collect();
+                before
+                    .move_placing(entry.player, &placed)
+                    .map(|game_move| state.play_move(&game_move))
+            }
+            Turn::Passed => Some(state.apply_action(Action::PassTurn)),
+        };
+        // the history does not match the game, nothing further can be annotated
+        if !matches!(played, Some(Ok(()))) {
+            break;
+        }
+
+        if is_solvable(&before, threshold) {
+            let best = solve_for(&before, entry.player);
+            annotations.push(Annotation {
+                turn,
+                player: entry.player,
+                best_margin: best.margin,
+                played_margin: solve_for(&state, entry.player).margin,
+                best_move: best.line.first().map(|(_, game_move)| game_move.clone()),
+            });
+        }
+    }
+
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_mode::{GameMode, TwoPlayerMap},
+        strategy::{Endgame, Greedy, Strategy},
+    };
+
+    use super::*;
+
+    /// a greedy game played until at most `threshold` legal moves are left
+    fn endgame(threshold: usize) -> GameState {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        while !is_solvable(&gs, threshold) {
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+        gs
+    }
+
+    /// plain minimax without pruning or a table
+    fn minimax(state: &GameState, player: Player) -> i8 {
+        if state.game_ended {
+            return state.score_margin(player);
+        }
+        let values = state
+            .legal_moves(state.player_state.current_player)
+            .into_iter()
+            .map(|game_move| {
+                let mut next = state.clone();
+                next.play_move(&game_move).unwrap();
+                minimax(&next, player)
+            });
+        if state.player_state.current_player == player {
+            values.max().unwrap()
+        } else {
+            values.min().unwrap()
+        }
+    }
+
+    #[test]
+    fn solves_endgames_exactly() {
+        let gs = endgame(12);
+        let solution = solve(&gs);
+        let player = gs.player_state.current_player;
+
+        assert_eq!(solution.margin, minimax(&gs, player));
+
+        // playing the line ends the game with the proven margin
+        let mut end = gs.clone();
+        for (mover, game_move) in &solution.line {
+            assert_eq!(end.player_state.current_player, *mover);
+            end.play_move(game_move).unwrap();
+        }
+        assert!(end.game_ended);
+        assert_eq!(end.score_margin(player), solution.margin);
+
+        let strategy = Endgame {
+            threshold: 12,
+            strategy: Greedy,
+        };
+        assert_eq!(
+            strategy.choose_move(&gs),
+            solution
+                .line
+                .first()
+                .map(|(_, game_move)| game_move.clone())
+        );
+    }
+
+    #[test]
+    fn annotates_the_endgame_of_a_game() {
+        let mut gs = endgame(12);
+        while !gs.game_ended {
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+
+        let annotations = annotate(&gs, 12);
+        assert!(!annotations.is_empty());
+        for annotation in &annotations {
+            assert!(annotation.played_margin <= annotation.best_margin);
+            assert!(annotation.best_move.is_some());
+        }
+        // the first annotated turn is the position the endgame started from
+        assert_eq!(annotations[0].best_margin, solve(&endgame(12)).margin);
+    }
+}
@@ -0,0 +1,315 @@
+//! Contains [Weights] and [evaluate], the heuristic shared by the search-based AIs
+//!
+//! Each feature is measured for a player relative to their strongest opponent, and the evaluation is the weighted sum of the features
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    bitboard::Bitboard,
+    board::{Board, Cube},
+    game_move::Move,
+    game_state::{legal_moves_on, GameState},
+    player::Player,
+    player_state::PlayerState,
+    rules::Rules,
+};
+
+/// How much each feature counts towards the evaluation
+///
+/// Serializable so tuned weights can be stored and shared between AIs
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+#[ts(export, export_to = "pkg/types/Weights.ts")]
+pub struct Weights {
+    pub column_ownership: f32,
+    pub contested_columns: f32,
+    /// generates every legal move of every player, leave at zero when evaluation speed matters
+    pub mobility: f32,
+    pub pieces_left: f32,
+    pub height_advantage: f32,
+    pub frontier: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            column_ownership: 1.0,
+            contested_columns: -0.25,
+            mobility: 0.0,
+            pieces_left: 0.05,
+            height_advantage: 0.1,
+            frontier: 0.05,
+        }
+    }
+}
+
+/// The features of a position for one player, each relative to the strongest opponent
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Default)]
+#[ts(export, export_to = "pkg/types/Features.ts")]
+pub struct Features {
+    /// columns whose top cube is the player's
+    pub column_ownership: f32,
+    /// owned columns that can still be covered
+    pub contested_columns: f32,
+    /// legal moves available
+    pub mobility: f32,
+    /// cubes in the pieces left in hand
+    pub pieces_left: f32,
+    /// summed height of the owned column tops, high tops are harder to cover
+    pub height_advantage: f32,
+    /// empty cells next to the player's cubes, where their next piece may go
+    pub frontier: f32,
+}
+
+impl Features {
+    /// the weighted sum of the features
+    pub fn weighted(&self, weights: &Weights) -> f32 {
+        self.column_ownership * weights.column_ownership
+            + self.contested_columns * weights.contested_columns
+            + self.mobility * weights.mobility
+            + self.pieces_left * weights.pieces_left
+            + self.height_advantage * weights.height_advantage
+            + self.frontier * weights.frontier
+    }
+}
+
+/// Evaluates the position for a player, higher is better
+pub fn evaluate(state: &GameState, player: Player, weights: &Weights) -> f32 {
+    features(state, player, weights).weighted(weights)
+}
+
+/// Evaluates the position after the current player plays a move, without playing it
+pub fn evaluate_after(
+    state: &GameState,
+    game_move: &Move,
+    player: Player,
+    weights: &Weights,
+) -> f32 {
+    let mover = state.player_state.current_player;
+
+    if weights.mobility != 0.0 {
+        // mobility needs the full position after the move
+        let mut board = state.board_state.board.clone();
+        let cubes: Vec<Cube> = game_move
+            .cubes()
+            .into_iter()
+            .map(|position| Cube {
+                player: mover,
+                position,
+                error: None,
+            })
+            .collect();
+        board.add_cubes(&cubes);
+
+        let mut player_state = state.player_state.clone();
+        player_state.remove_piece(mover, game_move.piece);
+
+        return board_features(&board, &player_state, &state.rules, player, weights)
+            .weighted(weights);
+    }
+
+    let board = &state.board_state.board;
+    let mut occupied = Player::ALL.map(|player| board.occupied_by(player));
+    occupied[mover.index()] |=
+        game_move.cubes().into_iter().collect::<Bitboard>() & board.in_bounds();
+
+    position_features(
+        board.in_bounds(),
+        occupied,
+        &state.player_state,
+        player,
+        |other| {
+            let remaining = remaining_cubes(&state.player_state, other);
+            match other == mover {
+                true => remaining - game_move.orientation.coords.len(),
+                false => remaining,
+            }
+        },
+        |_| 0,
+    )
+    .weighted(weights)
+}
+
+/// Measures the features of the position for a player
+///
+/// Mobility is only measured when it is weighted
+pub fn features(state: &GameState, player: Player, weights: &Weights) -> Features {
+    board_features(
+        &state.board_state.board,
+        &state.player_state,
+        &state.rules,
+        player,
+        weights,
+    )
+}
+
+fn board_features(
+    board: &Board,
+    player_state: &PlayerState,
+    rules: &Rules,
+    player: Player,
+    weights: &Weights,
+) -> Features {
+    position_features(
+        board.in_bounds(),
+        Player::ALL.map(|player| board.occupied_by(player)),
+        player_state,
+        player,
+        |other| remaining_cubes(player_state, other),
+        |other| match weights.mobility == 0.0 {
+            true => 0,
+            false => legal_moves_on(board, rules, player_state.players.get(&other), other).count(),
+        },
+    )
+}
+
+fn remaining_cubes(player_state: &PlayerState, player: Player) -> usize {
+    player_state
+        .players
+        .get(&player)
+        .map(|hand| hand.remaining_cubes())
+        .unwrap_or_default()
+}
+
+/// measures the features from the cells each player occupies
+fn position_features(
+    in_bounds: Bitboard,
+    occupied: [Bitboard; 4],
+    player_state: &PlayerState,
+    player: Player,
+    remaining_cubes: impl Fn(Player) -> usize,
+    mobility: impl Fn(Player) -> usize,
+) -> Features {
+    let all_occupied = occupied
+        .iter()
+        .fold(Bitboard::EMPTY, |acc, occupied| acc | *occupied);
+    let tops = all_occupied & !all_occupied.below();
+    let empty = in_bounds & !all_occupied;
+    // tops with an empty cell above them
+    let coverable = tops & empty.shift_down();
+
+    let measure = |other: Player| {
+        let occupied = occupied[other.index()];
+        let owned_tops = tops & occupied;
+        Features {
+            column_ownership: owned_tops.count() as f32,
+            contested_columns: (coverable & occupied).count() as f32,
+            mobility: mobility(other) as f32,
+            pieces_left: remaining_cubes(other) as f32,
+            height_advantage: owned_tops.iter().map(|top| top.y + 1).sum::<i32>() as f32,
+            frontier: (occupied.neighbours() & empty).count() as f32,
+        }
+    };
+
+    let own = measure(player);
+    let opponents: Vec<Features> = player_state
+        .players
+        .keys()
+        .filter(|other| **other != player)
+        .map(|other| measure(*other))
+        .collect();
+
+    // relative to the best opponent in each feature, as with the score margin
+    let best = |feature: fn(&Features) -> f32| {
+        opponents
+            .iter()
+            .map(feature)
+            .max_by(f32::total_cmp)
+            .unwrap_or_default()
+    };
+
+    Features {
+        column_ownership: own.column_ownership - best(|f| f.column_ownership),
+        contested_columns: own.contested_columns - best(|f| f.contested_columns),
+        mobility: own.mobility - best(|f| f.mobility),
+        pieces_left: own.pieces_left - best(|f| f.pieces_left),
+        height_advantage: own.height_advantage - best(|f| f.height_advantage),
+        frontier: own.frontier - best(|f| f.frontier),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, V3},
+        coord::Coord,
+        game_mode::{GameMode, TwoPlayerMap},
+        piece::PieceName,
+    };
+
+    use super::*;
+
+    #[test]
+    fn features_of_a_position() {
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower));
+        let weights = Weights {
+            mobility: 1.0,
+            ..Weights::default()
+        };
+        assert_eq!(features(&gs, Player::P1, &weights).column_ownership, 0.0);
+
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+
+        let p1 = features(&gs, Player::P1, &weights);
+        assert_eq!(p1.column_ownership, 2.0);
+        assert_eq!(p1.contested_columns, 2.0);
+        assert_eq!(p1.pieces_left, -2.0);
+        assert_eq!(p1.height_advantage, 2.0);
+        assert_eq!(p1.frontier, 5.0);
+        assert!(p1.mobility < 0.0);
+
+        let p2 = features(&gs, Player::P2, &weights);
+        assert_eq!(p2.column_ownership, -2.0);
+        assert_eq!(p2.pieces_left, 2.0);
+        assert_eq!(p2.mobility, -p1.mobility);
+    }
+
+    #[test]
+    fn evaluate_after_agrees_with_playing_the_move() {
+        let gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Pyramid));
+
+        let moves = gs.legal_moves(Player::P1);
+
+        // counting every legal move is slow, so mobility is checked on fewer moves
+        for (weights, step) in [
+            (Weights::default(), 17),
+            (
+                Weights {
+                    mobility: 0.01,
+                    ..Weights::default()
+                },
+                moves.len() / 2,
+            ),
+        ] {
+            for game_move in moves.iter().step_by(step) {
+                let mut next = gs.clone();
+                next.play_move(game_move).unwrap();
+                for player in [Player::P1, Player::P2] {
+                    assert_eq!(
+                        evaluate_after(&gs, game_move, player, &weights),
+                        evaluate(&next, player, &weights)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weights_serialize() {
+        let weights = Weights::default();
+        let json = serde_json::to_string(&weights).unwrap();
+        assert_eq!(serde_json::from_str::<Weights>(&json).unwrap(), weights);
+        assert_eq!(
+            serde_json::from_str::<Weights>(r#"{"mobility": 0.5}"#).unwrap(),
+            Weights {
+                mobility: 0.5,
+                ..Weights::default()
+            }
+        );
+    }
+}
@@ -1,8 +1,10 @@
 //! Contains [GameMode]
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use ts_rs::TS;
 
+use crate::player::Player;
+
 /// Represents game mode and map
 #[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(tag = "type", content = "data")]
@@ -10,7 +12,13 @@ use ts_rs::TS;
 pub enum GameMode {
     Solitaire(SolitaireMap),
     TwoPlayer(TwoPlayerMap),
-    VSGreedyAI(TwoPlayerMap),
+    /// against an AI opponent
+    VSGreedyAI(AIOpponent),
+    /// three or four players, each playing for themselves
+    FreeForAll {
+        map: FreeForAllMap,
+        player_count: PlayerCount,
+    },
 }
 
 impl Default for GameMode {
@@ -19,13 +27,33 @@ impl Default for GameMode {
     }
 }
 
+impl GameMode {
+    /// the players taking part, in turn order
+    pub fn players(&self) -> Vec<Player> {
+        Player::first(match self {
+            GameMode::Solitaire(_) => 1,
+            GameMode::TwoPlayer(_) | GameMode::VSGreedyAI(_) => 2,
+            GameMode::FreeForAll { player_count, .. } => match player_count {
+                PlayerCount::Three => 3,
+                PlayerCount::Four => 4,
+            },
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
 #[serde(tag = "type")]
 #[ts(export, export_to = "pkg/types/SolitaireMap.ts")]
 pub enum SolitaireMap {
     FourByFiveByTwo,
 }
-#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
+
+impl SolitaireMap {
+    /// every solitaire map
+    pub const ALL: [SolitaireMap; 1] = [SolitaireMap::FourByFiveByTwo];
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
 #[ts(export, export_to = "pkg/types/TwoPlayerMap.ts")]
 pub enum TwoPlayerMap {
     Tower,
@@ -33,3 +61,123 @@ pub enum TwoPlayerMap {
     Stairs,
     Wall,
 }
+
+impl TwoPlayerMap {
+    /// every two player map
+    pub const ALL: [TwoPlayerMap; 4] = [
+        TwoPlayerMap::Tower,
+        TwoPlayerMap::Pyramid,
+        TwoPlayerMap::Stairs,
+        TwoPlayerMap::Wall,
+    ];
+}
+
+/// The map and strength of the AI opponent in [GameMode::VSGreedyAI]
+///
+/// Also deserializes from just a [TwoPlayerMap], the form saved before difficulties were added, as a [Difficulty::Medium] opponent
+#[derive(Serialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
+#[ts(export, export_to = "pkg/types/AIOpponent.ts")]
+pub struct AIOpponent {
+    pub map: TwoPlayerMap,
+    pub difficulty: Difficulty,
+}
+
+impl From<TwoPlayerMap> for AIOpponent {
+    fn from(map: TwoPlayerMap) -> Self {
+        Self {
+            map,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for AIOpponent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Serialized {
+            Map(TwoPlayerMap),
+            Opponent {
+                map: TwoPlayerMap,
+                #[serde(default)]
+                difficulty: Difficulty,
+            },
+        }
+
+        Ok(match Serialized::deserialize(deserializer)? {
+            Serialized::Map(map) => map.into(),
+            Serialized::Opponent { map, difficulty } => Self { map, difficulty },
+        })
+    }
+}
+
+/// How strong the AI opponent plays
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[ts(export, export_to = "pkg/types/Difficulty.ts")]
+pub enum Difficulty {
+    /// random moves
+    Easy,
+    /// the move scoring best right away
+    #[default]
+    Medium,
+    /// the map's opening book while the game is in it, then a depth 2 alpha-beta search.
+    /// Built as [Opening](crate::strategy::Opening) around [AlphaBeta](crate::strategy::AlphaBeta)
+    Hard,
+    /// as [Difficulty::Hard], except that endgames small enough to solve are played perfectly instead of searched.
+    /// Built as [Opening](crate::strategy::Opening) around [Endgame](crate::strategy::Endgame) around [AlphaBeta](crate::strategy::AlphaBeta)
+    Expert,
+}
+
+impl Difficulty {
+    /// every difficulty, easiest first
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+}
+
+/// maps large enough for three or four players
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
+#[ts(export, export_to = "pkg/types/FreeForAllMap.ts")]
+pub enum FreeForAllMap {
+    Plaza,
+    Ziggurat,
+}
+
+impl FreeForAllMap {
+    /// every free for all map
+    pub const ALL: [FreeForAllMap; 2] = [FreeForAllMap::Plaza, FreeForAllMap::Ziggurat];
+}
+
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug)]
+#[ts(export, export_to = "pkg/types/PlayerCount.ts")]
+pub enum PlayerCount {
+    Three,
+    Four,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ai_opponent_serialization() {
+        // saved before difficulties were added
+        let legacy: GameMode =
+            serde_json::from_str(r#"{"type": "VSGreedyAI", "data": "Wall"}"#).unwrap();
+        assert_eq!(legacy, GameMode::VSGreedyAI(TwoPlayerMap::Wall.into()));
+
+        let expert = GameMode::VSGreedyAI(AIOpponent {
+            map: TwoPlayerMap::Stairs,
+            difficulty: Difficulty::Expert,
+        });
+        let json = serde_json::to_string(&expert).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"VSGreedyAI","data":{"map":"Stairs","difficulty":"Expert"}}"#
+        );
+        assert_eq!(serde_json::from_str::<GameMode>(&json).unwrap(), expert);
+    }
+}
@@ -0,0 +1,32 @@
+//! Contains [Move]
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    coord::Coord,
+    piece::{Piece, PieceName},
+};
+
+/// A legal placement of a piece on the board
+///
+/// Returned by [GameState::legal_moves](crate::game_state::GameState::legal_moves)
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/Move.ts")]
+pub struct Move {
+    pub piece: PieceName,
+    /// index into [PieceName::orientations]
+    pub orientation_id: usize,
+    /// the piece rotated into the orientation it is played in
+    pub orientation: Piece,
+    /// where the origin cube of the piece is placed
+    #[ts(type = "[number, number, number]")]
+    pub position: Coord,
+}
+
+impl Move {
+    /// positions of the cubes making up the placed piece
+    pub fn cubes(&self) -> Vec<Coord> {
+        self.orientation.get_moved_copy(self.position).coords
+    }
+}
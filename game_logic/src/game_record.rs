@@ -0,0 +1,636 @@
+//! Contains [GameRecord], a game written down as text to be stored and shared
+//!
+//! A record starts with a header of `[Tag "value"]` lines, followed by one token per turn taken, separated by whitespace
+//!
+//! ```
+//! use game_logic::game_record::GameRecord;
+//!
+//! let text = r#"[Mode "VSGreedyAI"]
+//! [Map "Tower"]
+//! [Difficulty "Hard"]
+//! [Rules "TouchOwn PieceSupport ColumnTops"]
+//! [Players "p1 p2"]
+//! [Seed "7"]
+//!
+//! z:11@0,0,1 z:11@0,1,1
+//! l:18@2,0,1 pass
+//! two_by_two:2@0,0,2
+//! "#;
+//!
+//! let record = GameRecord::parse(text).unwrap();
+//! assert_eq!(record.turns.len(), 5);
+//! assert_eq!(record.write(), text);
+//! ```
+//!
+//! - `Mode` and `Map` are required. `Difficulty` is only allowed for `VSGreedyAI` games and defaults to `Medium`
+//! - `Rules` names the touch rule, support rule and scoring method, the standard rules by default
+//! - `Players` lists the players in turn order. It sets the player count of `FreeForAll` games and must match the mode otherwise
+//! - `Seed` seeds the AI's choices, 0 by default
+//!
+//! Values are named as they are serialized, e.g. `two_by_two` for [PieceName::TwoByTwo]
+//!
+//! A turn is `pass`, or the piece, the id of its orientation (see [PieceName::orientations]) and the position of its origin cube.
+//! Everything after a `;` on a line is a comment
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    action::{Action, ActionError},
+    coord::Coord,
+    game_mode::{
+        AIOpponent, Difficulty, FreeForAllMap, GameMode, PlayerCount, SolitaireMap, TwoPlayerMap,
+    },
+    game_move::Move,
+    game_state::GameState,
+    history::Turn,
+    piece::PieceName,
+    rules::{Rules, ScoringMethod, SupportRule, TouchRule},
+};
+
+/// A game as the mode and rules it is played with and the turns taken
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    pub game_mode: GameMode,
+    pub rules: Rules,
+    pub seed: u64,
+    /// the turns taken, oldest first. None for a pass
+    pub turns: Vec<Option<Move>>,
+}
+
+/// Why a record could not be read, see [RecordError]
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/RecordErrorKind.ts")]
+pub enum RecordErrorKind {
+    /// a header line is not of the form `[Tag "value"]`
+    InvalidTag,
+    UnknownTag(String),
+    DuplicateTag(String),
+    MissingTag(String),
+    /// the value is not one the tag allows
+    InvalidValue {
+        tag: String,
+        value: String,
+    },
+    /// the token is neither `pass` nor a move
+    InvalidTurn(String),
+    UnknownPiece(String),
+    UnknownOrientation {
+        piece: PieceName,
+        orientation_id: usize,
+    },
+    /// the turn cannot be taken in the game as replayed so far
+    IllegalTurn(ActionError),
+}
+
+/// An error in a record, at a line and column counted from 1
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/RecordError.ts")]
+pub struct RecordError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: RecordErrorKind,
+}
+
+/// The result of reading a record, as returned across the WASM boundary
+#[derive(Serialize, TS)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/RecordOutcome.ts")]
+pub enum RecordOutcome {
+    Ok(Box<GameState>),
+    Err(RecordError),
+}
+
+impl GameRecord {
+    /// The record of the turns taken in a game, undone turns are left out
+    pub fn from_game(game: &GameState) -> Self {
+        let mut state = GameState::with_rules(game.game_mode, game.rules).with_seed(game.seed);
+        let mut turns = vec![];
+
+        for entry in game.history.turns() {
+            let turn = match &entry.turn {
+                Turn::Played { cubes, .. } => {
+                    let placed: Vec<Coord> = cubes.iter().map(|cube| cube.position).collect();
+                    match state.move_placing(entry.player, &placed) {
+                        Some(game_move) => Some(game_move),
+                        // the history does not match the game, nothing further can be recorded
+                        None => break,
+                    }
+                }
+                Turn::Passed => None,
+            };
+            if take_turn(&mut state, &turn).is_err() {
+                break;
+            }
+            turns.push(turn);
+        }
+
+        Self {
+            game_mode: game.game_mode,
+            rules: game.rules,
+            seed: game.seed,
+            turns,
+        }
+    }
+
+    /// Plays the recorded turns from the start of a new game
+    pub fn replay(&self) -> Result<GameState, ActionError> {
+        let mut state = GameState::with_rules(self.game_mode, self.rules).with_seed(self.seed);
+        for turn in &self.turns {
+            take_turn(&mut state, turn)?;
+        }
+        Ok(state)
+    }
+
+    /// Writes the record as text, a line of turns for each round of play
+    pub fn write(&self) -> String {
+        let (mode, map, difficulty) = match self.game_mode {
+            GameMode::Solitaire(map) => ("Solitaire", name(&map), None),
+            GameMode::TwoPlayer(map) => ("TwoPlayer", name(&map), None),
+            GameMode::VSGreedyAI(opponent) => {
+                ("VSGreedyAI", name(&opponent.map), Some(opponent.difficulty))
+            }
+            GameMode::FreeForAll { map, .. } => ("FreeForAll", name(&map), None),
+        };
+        let players = self.game_mode.players();
+
+        let mut text = format!("[Mode \"{mode}\"]\n[Map \"{map}\"]\n");
+        if let Some(difficulty) = difficulty {
+            text += &format!("[Difficulty \"{}\"]\n", name(&difficulty));
+        }
+        text += &format!(
+            "[Rules \"{} {} {}\"]\n[Players \"{}\"]\n[Seed \"{}\"]\n\n",
+            name(&self.rules.touch),
+            name(&self.rules.support),
+            name(&self.rules.scoring),
+            players.iter().map(name).collect::<Vec<_>>().join(" "),
+            self.seed,
+        );
+
+        for round in self.turns.chunks(players.len()) {
+            let tokens: Vec<String> = round.iter().map(turn_token).collect();
+            text += &tokens.join(" ");
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Reads a record written by [GameRecord::write], replaying it to check that every turn can be taken
+    pub fn parse(text: &str) -> Result<Self, RecordError> {
+        Self::parse_and_replay(text).map(|(record, _)| record)
+    }
+
+    /// [GameRecord::parse], also returning the game the record was replayed to
+    pub fn parse_and_replay(text: &str) -> Result<(Self, GameState), RecordError> {
+        let mut tags: Vec<Tag> = vec![];
+        let mut tokens: Vec<Token> = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split(';').next().unwrap_or_default();
+            let trimmed = line.trim_start();
+            let indent = line.chars().count() - trimmed.chars().count();
+
+            // tags come before the first turn
+            if trimmed.starts_with('[') && tokens.is_empty() {
+                let tag = parse_tag(trimmed.trim_end(), line_number, indent + 1)?;
+                if tags.iter().any(|other| other.name == tag.name) {
+                    return Err(RecordError {
+                        line: line_number,
+                        column: indent + 1,
+                        kind: RecordErrorKind::DuplicateTag(tag.name),
+                    });
+                }
+                tags.push(tag);
+                continue;
+            }
+
+            let mut start = None;
+            for (column, c) in line.chars().chain(std::iter::once(' ')).enumerate() {
+                match (c.is_whitespace(), start) {
+                    (false, None) => start = Some(column),
+                    (true, Some(first)) => {
+                        tokens.push(Token {
+                            text: line.chars().skip(first).take(column - first).collect(),
+                            line: line_number,
+                            column: first + 1,
+                        });
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // where the turns start, missing tags are reported there
+        let (line, column) = tokens.first().map_or_else(
+            || (text.lines().count() + 1, 1),
+            |token| (token.line, token.column),
+        );
+        let header = Header { tags, line, column };
+        let record = Self {
+            game_mode: header.game_mode()?,
+            rules: header.rules()?,
+            seed: header.seed()?,
+            turns: vec![],
+        };
+
+        let mut state = record
+            .replay()
+            .expect("a record without turns can be replayed");
+        let mut turns = vec![];
+        for token in tokens {
+            let turn = token.turn()?;
+            take_turn(&mut state, &turn)
+                .map_err(|e| token.error(RecordErrorKind::IllegalTurn(e)))?;
+            turns.push(turn);
+        }
+
+        Ok((Self { turns, ..record }, state))
+    }
+}
+
+/// plays a move, or passes for None
+fn take_turn(state: &mut GameState, turn: &Option<Move>) -> Result<(), ActionError> {
+    match turn {
+        Some(game_move) => state.play_move(game_move),
+        None => state.apply_action(Action::PassTurn),
+    }
+}
+
+fn turn_token(turn: &Option<Move>) -> String {
+    match turn {
+        Some(game_move) => format!(
+            "{}:{}@{},{},{}",
+            name(&game_move.piece),
+            game_move.orientation_id,
+            game_move.position.x,
+            game_move.position.y,
+            game_move.position.z
+        ),
+        None => "pass".to_string(),
+    }
+}
+
+/// the name a value is serialized with, the `type` of values serialized as an object
+///
+/// Records use the names the values have in the TypeScript types, so they stay the same when a variant is renamed in Rust only
+fn name<T: Serialize>(value: &T) -> String {
+    let value = serde_json::to_value(value).expect("record values serialize");
+    value
+        .get("type")
+        .unwrap_or(&value)
+        .as_str()
+        .expect("record values serialize to a name")
+        .to_string()
+}
+
+/// the value whose name is `value_name`
+fn named<T: Serialize + Copy>(values: &[T], value_name: &str) -> Option<T> {
+    values
+        .iter()
+        .find(|value| name(*value) == value_name)
+        .copied()
+}
+
+/// a `[Tag "value"]` line of the header
+struct Tag {
+    name: String,
+    value: String,
+    line: usize,
+    /// column of the value
+    column: usize,
+}
+
+impl Tag {
+    fn invalid(&self) -> RecordError {
+        RecordError {
+            line: self.line,
+            column: self.column,
+            kind: RecordErrorKind::InvalidValue {
+                tag: self.name.clone(),
+                value: self.value.clone(),
+            },
+        }
+    }
+}
+
+const TAGS: [&str; 6] = ["Mode", "Map", "Difficulty", "Rules", "Players", "Seed"];
+
+fn parse_tag(text: &str, line: usize, column: usize) -> Result<Tag, RecordError> {
+    let invalid = |kind| RecordError { line, column, kind };
+
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .ok_or(invalid(RecordErrorKind::InvalidTag))?;
+    let (name, value) = inner
+        .split_once(char::is_whitespace)
+        .ok_or(invalid(RecordErrorKind::InvalidTag))?;
+    let quoted = value.trim_start();
+    let value = quoted
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .filter(|value| !value.contains('"'))
+        .ok_or(invalid(RecordErrorKind::InvalidTag))?;
+    if !TAGS.contains(&name) {
+        return Err(invalid(RecordErrorKind::UnknownTag(name.to_string())));
+    }
+
+    Ok(Tag {
+        name: name.to_string(),
+        value: value.to_string(),
+        line,
+        // after the bracket, the name, the whitespace and the opening quote
+        column: column + 1 + inner.chars().count() - quoted.chars().count() + 1,
+    })
+}
+
+/// the tags of a record, with the position the turns start at
+struct Header {
+    tags: Vec<Tag>,
+    line: usize,
+    column: usize,
+}
+
+impl Header {
+    fn get(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    fn require(&self, name: &str) -> Result<&Tag, RecordError> {
+        self.get(name).ok_or(RecordError {
+            line: self.line,
+            column: self.column,
+            kind: RecordErrorKind::MissingTag(name.to_string()),
+        })
+    }
+
+    fn game_mode(&self) -> Result<GameMode, RecordError> {
+        let mode = self.require("Mode")?;
+        let map = self.require("Map")?;
+        let players = self.get("Players");
+        let difficulty = match self.get("Difficulty") {
+            Some(tag) if mode.value != "VSGreedyAI" => return Err(tag.invalid()),
+            Some(tag) => named(&Difficulty::ALL, &tag.value).ok_or(tag.invalid())?,
+            None => Difficulty::default(),
+        };
+
+        let game_mode = match mode.value.as_str() {
+            "Solitaire" => {
+                GameMode::Solitaire(named(&SolitaireMap::ALL, &map.value).ok_or(map.invalid())?)
+            }
+            "TwoPlayer" => {
+                GameMode::TwoPlayer(named(&TwoPlayerMap::ALL, &map.value).ok_or(map.invalid())?)
+            }
+            "VSGreedyAI" => GameMode::VSGreedyAI(AIOpponent {
+                map: named(&TwoPlayerMap::ALL, &map.value).ok_or(map.invalid())?,
+                difficulty,
+            }),
+            "FreeForAll" => {
+                let count = self.require("Players")?;
+                let player_count = match count.value.split_whitespace().count() {
+                    3 => PlayerCount::Three,
+                    4 => PlayerCount::Four,
+                    _ => return Err(count.invalid()),
+                };
+                GameMode::FreeForAll {
+                    map: named(&FreeForAllMap::ALL, &map.value).ok_or(map.invalid())?,
+                    player_count,
+                }
+            }
+            _ => return Err(mode.invalid()),
+        };
+
+        match players {
+            Some(tag)
+                if !tag
+                    .value
+                    .split_whitespace()
+                    .eq(game_mode.players().iter().map(name)) =>
+            {
+                Err(tag.invalid())
+            }
+            _ => Ok(game_mode),
+        }
+    }
+
+    fn rules(&self) -> Result<Rules, RecordError> {
+        let Some(tag) = self.get("Rules") else {
+            return Ok(Rules::default());
+        };
+
+        match tag.value.split_whitespace().collect::<Vec<_>>()[..] {
+            [touch, support, scoring] => Ok(Rules {
+                touch: named(&TouchRule::ALL, touch).ok_or(tag.invalid())?,
+                support: named(&SupportRule::ALL, support).ok_or(tag.invalid())?,
+                scoring: named(&ScoringMethod::ALL, scoring).ok_or(tag.invalid())?,
+            }),
+            _ => Err(tag.invalid()),
+        }
+    }
+
+    fn seed(&self) -> Result<u64, RecordError> {
+        self.get("Seed")
+            .map_or(Ok(0), |tag| tag.value.parse().map_err(|_| tag.invalid()))
+    }
+}
+
+/// a turn of a record, as written
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, kind: RecordErrorKind) -> RecordError {
+        RecordError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    /// the move named by the token, None for a pass
+    fn turn(&self) -> Result<Option<Move>, RecordError> {
+        if self.text == "pass" {
+            return Ok(None);
+        }
+        let invalid = || self.error(RecordErrorKind::InvalidTurn(self.text.clone()));
+
+        let (piece, rest) = self.text.split_once(':').ok_or_else(invalid)?;
+        let (orientation_id, position) = rest.split_once('@').ok_or_else(invalid)?;
+        let orientation_id: usize = orientation_id.parse().map_err(|_| invalid())?;
+        let position: Vec<i32> = position
+            .split(',')
+            .map(|component| component.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [x, y, z] = position[..] else {
+            return Err(invalid());
+        };
+
+        let piece = named(&PieceName::ALL, piece)
+            .ok_or_else(|| self.error(RecordErrorKind::UnknownPiece(piece.to_string())))?;
+        let orientation = piece.orientation(orientation_id).ok_or_else(|| {
+            self.error(RecordErrorKind::UnknownOrientation {
+                piece,
+                orientation_id,
+            })
+        })?;
+
+        Ok(Some(Move {
+            piece,
+            orientation_id,
+            orientation: orientation.clone(),
+            position: Coord::new(x, y, z),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        player::Player,
+        strategy::{Greedy, Strategy},
+    };
+
+    use super::*;
+
+    /// a game played to the end by the greedy strategy, with a pass by the first player
+    fn played_game(game_mode: GameMode, rules: Rules) -> GameState {
+        let mut gs = GameState::with_rules(game_mode, rules).with_seed(7);
+        gs.apply_action(Action::PassTurn).unwrap();
+        while !gs.game_ended {
+            let game_move = Greedy.choose_move(&gs);
+            take_turn(&mut gs, &game_move).unwrap();
+        }
+        gs
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let games = [
+            played_game(
+                GameMode::VSGreedyAI(AIOpponent {
+                    map: TwoPlayerMap::Stairs,
+                    difficulty: Difficulty::Hard,
+                }),
+                Rules::default(),
+            ),
+            played_game(
+                GameMode::FreeForAll {
+                    map: FreeForAllMap::Plaza,
+                    player_count: PlayerCount::Three,
+                },
+                Rules {
+                    touch: TouchRule::TouchAny,
+                    support: SupportRule::Gravity,
+                    scoring: ScoringMethod::CubeCount,
+                },
+            ),
+        ];
+
+        for game in games {
+            let record = GameRecord::from_game(&game);
+            assert_eq!(record.turns.len(), game.history.turns().len());
+            assert_eq!(record.turns[0], None);
+
+            let text = record.write();
+            let parsed = GameRecord::parse(&text).unwrap();
+            assert_eq!(parsed, record);
+
+            let replayed = parsed.replay().unwrap();
+            let (_, parsed_game) = GameRecord::parse_and_replay(&text).unwrap();
+            assert_eq!(parsed_game.hash(), replayed.hash());
+            assert!(replayed.game_ended);
+            assert_eq!(replayed.hash(), game.hash());
+            assert_eq!(replayed.score, game.score);
+            assert_eq!(GameRecord::from_game(&replayed).write(), text);
+        }
+    }
+
+    #[test]
+    fn parses_comments_and_defaults() {
+        let text = "; a game\n[Map \"Tower\"]\n  [Mode \"TwoPlayer\"] ; tags in any order\n\n  pass\tpass ; both pass\n";
+        let record = GameRecord::parse(text).unwrap();
+
+        assert_eq!(record.game_mode, GameMode::TwoPlayer(TwoPlayerMap::Tower));
+        assert_eq!(record.rules, Rules::default());
+        assert_eq!(record.seed, 0);
+        assert_eq!(record.turns, vec![None, None]);
+    }
+
+    #[test]
+    fn reports_errors_with_their_position() {
+        let error = |text: &str| {
+            let error = GameRecord::parse(text).unwrap_err();
+            (error.line, error.column, error.kind)
+        };
+        let header = "[Mode \"TwoPlayer\"]\n[Map \"Tower\"]\n";
+
+        let start = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower));
+        let first = turn_token(&start.legal_moves(Player::P1).first().cloned());
+        // the second player places a piece on the cubes of the first
+        assert!(matches!(
+            error(&format!("{header}\n{first}  {first}")),
+            (4, column, RecordErrorKind::IllegalTurn(ActionError::InvalidPreview(_)))
+                if column == first.len() + 3
+        ));
+
+        assert_eq!(
+            error(&format!("{header}pass square:0@0,0,0")),
+            (3, 6, RecordErrorKind::UnknownPiece("square".to_string()))
+        );
+        assert_eq!(
+            error(&format!("{header}one_by_two:99@0,0,0")),
+            (
+                3,
+                1,
+                RecordErrorKind::UnknownOrientation {
+                    piece: PieceName::OneByTwo,
+                    orientation_id: 99
+                }
+            )
+        );
+        assert_eq!(
+            error(&format!("{header} one_by_two@0,0")),
+            (
+                3,
+                2,
+                RecordErrorKind::InvalidTurn("one_by_two@0,0".to_string())
+            )
+        );
+        assert_eq!(
+            error("[Mode \"TwoPlayer\"]\n[Map \"Castle\"]\n"),
+            (
+                2,
+                7,
+                RecordErrorKind::InvalidValue {
+                    tag: "Map".to_string(),
+                    value: "Castle".to_string()
+                }
+            )
+        );
+        assert_eq!(
+            error("[Mode \"TwoPlayer\"]\n\npass"),
+            (3, 1, RecordErrorKind::MissingTag("Map".to_string()))
+        );
+        assert_eq!(
+            error(&format!("{header}[Players \"p1 p2 p3\"]")),
+            (
+                3,
+                11,
+                RecordErrorKind::InvalidValue {
+                    tag: "Players".to_string(),
+                    value: "p1 p2 p3".to_string()
+                }
+            )
+        );
+        assert_eq!(
+            error(&format!("{header}  [Map Tower]")),
+            (3, 3, RecordErrorKind::InvalidTag)
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Contains [GameResult], recorded when a game ends
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use crate::player::Player;
+
+/// The result of a finished game
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/GameResult.ts")]
+pub struct GameResult {
+    pub outcome: Outcome,
+    pub final_score: HashMap<Player, i8>,
+    pub reason: EndReason,
+}
+
+/// Who won
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/Outcome.ts")]
+pub enum Outcome {
+    Winner(Player),
+    /// the players sharing the highest score
+    Draw(Vec<Player>),
+    /// solitaire games have no winner, only how much of the map was filled
+    Solitaire(SolitaireResult),
+}
+
+/// The outcome of a solitaire game
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/SolitaireResult.ts")]
+pub enum SolitaireResult {
+    /// every cell of the map was filled
+    Completed,
+    /// no remaining piece could be played
+    Incomplete { empty_cells: u32 },
+}
+
+/// Why the game ended
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[ts(export, export_to = "pkg/types/EndReason.ts")]
+pub enum EndReason {
+    /// every cell of the map is filled
+    BoardFilled,
+    /// every player has played all of their pieces
+    AllPiecesPlayed,
+    /// pieces remain but none of them can be played
+    NoLegalMoves,
+}
+
+impl GameResult {
+    /// Determines the outcome of a multiplayer game from the final score
+    ///
+    /// `players` is in turn order, which is the order tied players are listed in
+    pub fn from_score(
+        players: &[Player],
+        final_score: HashMap<Player, i8>,
+        reason: EndReason,
+    ) -> Self {
+        let best = players
+            .iter()
+            .filter_map(|player| final_score.get(player))
+            .max()
+            .copied()
+            .unwrap_or_default();
+
+        let leaders: Vec<Player> = players
+            .iter()
+            .filter(|player| final_score.get(player).copied().unwrap_or_default() == best)
+            .copied()
+            .collect();
+
+        Self {
+            outcome: match leaders[..] {
+                [winner] => Outcome::Winner(winner),
+                _ => Outcome::Draw(leaders),
+            },
+            final_score,
+            reason,
+        }
+    }
+
+    /// The result of a solitaire game given the number of cells left unfilled
+    pub fn solitaire(
+        final_score: HashMap<Player, i8>,
+        empty_cells: u32,
+        reason: EndReason,
+    ) -> Self {
+        Self {
+            outcome: Outcome::Solitaire(match empty_cells {
+                0 => SolitaireResult::Completed,
+                empty_cells => SolitaireResult::Incomplete { empty_cells },
+            }),
+            final_score,
+            reason,
+        }
+    }
+
+    /// How good the result is for a player, from 0 to 1
+    ///
+    /// A win is worth 1 and a draw is shared between the tied players. A solitaire game is worth more the fewer cells are left empty
+    pub fn reward(&self, player: Player) -> f32 {
+        match &self.outcome {
+            Outcome::Winner(winner) => (*winner == player) as u8 as f32,
+            Outcome::Draw(players) if players.contains(&player) => 1.0 / players.len() as f32,
+            Outcome::Draw(_) => 0.0,
+            Outcome::Solitaire(SolitaireResult::Completed) => 1.0,
+            Outcome::Solitaire(SolitaireResult::Incomplete { empty_cells }) => {
+                1.0 / (1 + empty_cells) as f32
+            }
+        }
+    }
+}
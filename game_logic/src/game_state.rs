@@ -1,19 +1,30 @@
 //! Contains [GameState] which represents the state of the game
 
 use itertools::Itertools;
-use nalgebra::Vector3;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, ops::Index};
+use std::collections::HashMap;
 use ts_rs::TS;
 
 use crate::{
-    action::{Action, V3},
+    action::{Action, ActionError, V3},
+    board::{Board, CubeError},
     board_state::BoardState,
-    game_mode::GameMode,
+    coord::Coord,
+    evaluation::Weights,
+    game_mode::{Difficulty, GameMode},
+    game_move::Move,
+    game_result::{EndReason, GameResult},
+    history::{History, HistoryEntry, Turn},
     piece::PieceName,
     player::Player,
+    player_hand_state::PlayerHandState,
     player_state::PlayerState,
     rotation_axis::RotationAxis,
+    rules::Rules,
+    search,
+    strategy::{self, AlphaBeta, Greedy, Strategy},
 };
 
 /// Represents the state of the game
@@ -23,46 +34,121 @@ pub struct GameState {
     pub(crate) player_state: PlayerState,
     pub(crate) board_state: BoardState,
     pub(crate) game_mode: GameMode,
+    /// the rule set every placement and score is checked against
+    #[serde(default)]
+    pub(crate) rules: Rules,
     pub(crate) score: HashMap<Player, i8>,
     pub(crate) game_ended: bool,
+    /// turns that can be undone / redone
+    #[serde(default)]
+    pub(crate) history: History,
+    /// set when the game ends
+    #[serde(default)]
+    pub(crate) result: Option<GameResult>,
+    /// seeds the random choices of the AI, see [GameState::rng]
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub(crate) seed: u64,
 }
 
 impl GameState {
     pub fn new(game_mode: GameMode) -> Self {
+        Self::with_rules(game_mode, Rules::default())
+    }
+
+    /// Starts a game played with a variant rule set
+    pub fn with_rules(game_mode: GameMode, rules: Rules) -> Self {
         Self {
             game_mode,
+            rules,
             player_state: PlayerState::new(game_mode),
             board_state: BoardState::new(game_mode),
-            score: match game_mode {
-                GameMode::Solitaire(_) => HashMap::from([(Player::P1, 0)]),
-                _ => HashMap::from([(Player::P1, 0), (Player::P2, 0)]),
-            },
+            score: game_mode
+                .players()
+                .into_iter()
+                .map(|player| (player, 0))
+                .collect(),
             game_ended: false,
+            history: History::default(),
+            result: None,
+            seed: 0,
         }
     }
 
+    /// The game with the AI's random choices seeded by `seed`
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { seed, ..self }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A random number generator for the AI's choices in the current position, such as breaking ties between equally good moves
+    ///
+    /// Seeded by the game's seed and the position's hash, so replaying a game with the same seed replays the same choices
+    pub fn rng(&self) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(self.seed ^ self.hash())
+    }
+
     /// Takes an Action performed by a player and updates the state of the game
-    pub fn apply_action(&mut self, action: Action) {
+    ///
+    /// If the action cannot be applied the state is left unchanged and the reason is returned
+    pub fn apply_action(&mut self, action: Action) -> Result<(), ActionError> {
+        if self.game_ended && !matches!(action, Action::Reset | Action::Undo | Action::Redo) {
+            return Err(ActionError::GameEnded);
+        }
+
         match action {
             Action::SelectPiece(name) => self.select_piece(name),
-            Action::ClearSelectedPiece => self.clear_selected_piece(),
+            Action::ClearSelectedPiece => {
+                self.clear_selected_piece();
+                Ok(())
+            }
             Action::SetSelectedPieceOrigin(V3(new_origin)) => {
                 self.set_selected_piece_origin(new_origin)
             }
             Action::RotateSelectedPiece(rotation_axis) => self.rotate_selected_piece(rotation_axis),
             Action::PreviewPiece(V3(position)) => self.preview_piece(position),
-            Action::PlayPreviewedPiece => {
-                let _ = self.play_previewed_piece();
+            Action::PlayPreviewedPiece => self.play_previewed_piece(),
+            Action::PassTurn => match self.game_mode {
+                GameMode::Solitaire(_) => Err(ActionError::WrongGameMode(self.game_mode)),
+                _ => {
+                    self.pass_turn();
+                    Ok(())
+                }
+            },
+            Action::Reset => {
+                self.reset();
+                Ok(())
+            }
+            Action::MakeGreedyAIMove => self.make_opponent_ai_move(&Greedy),
+            Action::MakeAIMove => {
+                // other game modes are rejected by make_opponent_ai_move
+                let difficulty = match self.game_mode {
+                    GameMode::VSGreedyAI(opponent) => opponent.difficulty,
+                    _ => Difficulty::default(),
+                };
+                self.make_opponent_ai_move(strategy::for_difficulty(difficulty).as_ref())
             }
-            Action::PassTurn => self.pass_turn(),
-            Action::Reset => self.reset(),
-            Action::MakeGreedyAIMove => self.make_greedy_ai_move(),
+            Action::MakeSearchAIMove(depth) => self.make_opponent_ai_move(&AlphaBeta {
+                depth: depth.min(search::MAX_DEPTH),
+                weights: Weights::default(),
+            }),
+            Action::MakeMCTSAIMove(config) => self.make_opponent_ai_move(&config),
+            Action::MakeStrategyAIMove(name) => match strategy::by_name(&name) {
+                Some(strategy) => self.make_opponent_ai_move(strategy.as_ref()),
+                None => Err(ActionError::UnknownStrategy(name)),
+            },
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
         }
     }
 
-    fn select_piece(&mut self, piece_name: PieceName) {
-        self.player_state.select_piece(piece_name);
-        self.board_state.clear_previewed_piece()
+    fn select_piece(&mut self, piece_name: PieceName) -> Result<(), ActionError> {
+        self.player_state.select_piece(piece_name)?;
+        self.board_state.clear_previewed_piece();
+        Ok(())
     }
 
     fn clear_selected_piece(&mut self) {
@@ -70,151 +156,928 @@ impl GameState {
         self.player_state.clear_selected_piece();
     }
 
-    fn rotate_selected_piece(&mut self, rotation_axis: RotationAxis) {
-        self.player_state.rotate_selected_piece(rotation_axis);
-        self.board_state.clear_previewed_piece()
+    fn rotate_selected_piece(&mut self, rotation_axis: RotationAxis) -> Result<(), ActionError> {
+        self.player_state.rotate_selected_piece(rotation_axis)?;
+        self.board_state.clear_previewed_piece();
+        Ok(())
     }
 
-    fn set_selected_piece_origin(&mut self, new_origin: Vector3<f32>) {
+    fn set_selected_piece_origin(&mut self, new_origin: Coord) -> Result<(), ActionError> {
         self.player_state.set_selected_piece_origin(new_origin)
     }
 
-    fn preview_piece(&mut self, position: Vector3<f32>) {
-        if let Some((current_player, piece)) = self.player_state.get_selected_piece() {
-            self.board_state
-                .preview_piece(current_player, piece, position)
-        }
+    fn preview_piece(&mut self, position: Coord) -> Result<(), ActionError> {
+        let (current_player, piece) = self
+            .player_state
+            .get_selected_piece()
+            .ok_or(ActionError::NoPieceSelected)?;
+
+        self.board_state
+            .preview_piece(&self.rules, current_player, piece, position);
+        Ok(())
     }
 
-    fn play_previewed_piece(&mut self) -> Result<(), ()> {
-        if let Ok(()) = self.board_state.play_selected_piece() {
-            self.player_state.play_selected_piece();
-            self.score = self.board_state.calculate_score();
-            Ok(())
-        } else {
-            Err(())
-        }
+    fn play_previewed_piece(&mut self) -> Result<(), ActionError> {
+        let (player, piece_name, piece) = match (
+            self.player_state.get_selected_piece_name(),
+            self.player_state.get_selected_piece(),
+        ) {
+            (Some(piece_name), Some((player, piece))) => (player, piece_name, piece),
+            _ => return Err(ActionError::NoPieceSelected),
+        };
+        let cubes = self.board_state.previewed_piece.clone().unwrap_or_default();
+
+        self.board_state.play_selected_piece()?;
+        self.player_state.play_selected_piece();
+        self.update_score();
+        self.history.push(HistoryEntry {
+            player,
+            turn: Turn::Played {
+                piece_name,
+                piece,
+                cubes,
+            },
+            by_ai: false,
+        });
+        self.determine_game_ended();
+        Ok(())
     }
 
     fn pass_turn(&mut self) {
-        println!("turn passed");
+        self.history.push(HistoryEntry {
+            player: self.player_state.current_player,
+            turn: Turn::Passed,
+            by_ai: false,
+        });
+        self.end_turn();
+    }
+
+    /// hands the turn to the next player
+    fn end_turn(&mut self) {
         self.board_state.clear_previewed_piece();
-        self.player_state.toggle_current_player();
+        self.player_state.advance_current_player();
         self.determine_game_ended();
     }
 
+    /// reverts the most recent turn. When playing against an AI, the AI's reply is reverted as well
+    fn undo(&mut self) -> Result<(), ActionError> {
+        let undone = self.history.undo(self.is_vs_ai());
+        if undone.is_empty() {
+            return Err(ActionError::NothingToUndo);
+        }
+
+        for entry in undone {
+            if let Turn::Played {
+                piece_name,
+                piece,
+                cubes,
+            } = entry.turn
+            {
+                self.board_state.board.remove_cubes(&cubes);
+                self.player_state
+                    .return_piece(entry.player, piece_name, piece);
+            }
+            self.player_state.set_current_player(entry.player);
+        }
+
+        self.board_state.clear_previewed_piece();
+        self.update_score();
+        self.game_ended = false;
+        self.result = None;
+        Ok(())
+    }
+
+    /// replays the most recently undone turn. When playing against an AI, the AI's reply is replayed as well
+    fn redo(&mut self) -> Result<(), ActionError> {
+        let redone = self.history.redo(self.is_vs_ai());
+        if redone.is_empty() {
+            return Err(ActionError::NothingToRedo);
+        }
+
+        for entry in redone {
+            self.player_state.set_current_player(entry.player);
+            match entry.turn {
+                Turn::Played {
+                    piece_name, cubes, ..
+                } => {
+                    self.board_state.board.add_cubes(&cubes);
+                    self.player_state.remove_piece(entry.player, piece_name);
+                    self.player_state.advance_current_player();
+                    self.update_score();
+                    self.determine_game_ended();
+                }
+                Turn::Passed => self.end_turn(),
+            }
+        }
+        self.board_state.clear_previewed_piece();
+        Ok(())
+    }
+
+    fn is_vs_ai(&self) -> bool {
+        matches!(self.game_mode, GameMode::VSGreedyAI(_))
+    }
+
     fn reset(&mut self) {
-        self.player_state = PlayerState::new(self.game_mode);
-        self.board_state = BoardState::default();
+        *self = GameState::with_rules(self.game_mode, self.rules).with_seed(self.seed);
+    }
+
+    /// scores the board for the players in the game
+    fn update_score(&mut self) {
+        let players = &self.player_state.players;
+        self.score = self.board_state.calculate_score(&self.rules);
+        self.score.retain(|player, _| players.contains_key(player));
     }
 
+    /// Run after every turn. Ends the game if no player can move, otherwise skips players without a legal move
+    ///
+    /// Players are checked in turn order from the player to move, stopping at the first one who can move.
+    /// Skipped turns are not recorded in the history, they follow from the board
     fn determine_game_ended(&mut self) {
-        if !self.available_move_exists(Player::P1) && !self.available_move_exists(Player::P2) {
-            self.game_ended = true;
+        let players = self.player_state.players_in_turn_order();
+        let current_player = self.player_state.current_player;
+        let start = players
+            .iter()
+            .position(|player| *player == current_player)
+            .unwrap_or_default();
+
+        let board_filled = self.board_state.board.empty_cells().is_empty();
+        let next_player_with_move = players
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(players.len())
+            .find(|player| !board_filled && self.has_legal_move(**player));
+
+        match next_player_with_move {
+            Some(player) if *player != current_player => {
+                self.player_state.set_current_player(*player)
+            }
+            Some(_) => {}
+            None => {
+                let reason = if board_filled {
+                    EndReason::BoardFilled
+                } else if self
+                    .player_state
+                    .players
+                    .values()
+                    .all(|hand| !hand.has_available_pieces())
+                {
+                    EndReason::AllPiecesPlayed
+                } else {
+                    EndReason::NoLegalMoves
+                };
+                self.end_game(reason);
+            }
         }
     }
 
-    fn available_move_exists(&self, player: Player) -> bool {
+    fn end_game(&mut self, reason: EndReason) {
+        self.game_ended = true;
+        self.result = Some(match self.game_mode {
+            GameMode::Solitaire(_) => GameResult::solitaire(
+                self.score.clone(),
+                self.board_state.board.empty_cells().count(),
+                reason,
+            ),
+            _ => GameResult::from_score(
+                &self.player_state.players_in_turn_order(),
+                self.score.clone(),
+                reason,
+            ),
+        });
+    }
+
+    pub fn current_player(&self) -> Player {
+        self.player_state.current_player
+    }
+
+    pub fn game_ended(&self) -> bool {
+        self.game_ended
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board_state.board
+    }
+
+    /// The pieces a player has yet to play, in [PieceName] order
+    pub fn remaining_pieces(&self, player: Player) -> Vec<PieceName> {
         self.player_state
             .players
-            .index(&player)
-            .get_available_piece_rotations()
-            .into_iter()
-            .cartesian_product(self.board_state.board.get_available_positions().into_iter())
-            .filter_map(|((piece_name, piece), position)| {
-                let mut next_game_state = self.clone();
-                next_game_state.apply_action(Action::SelectPiece(piece_name));
-                next_game_state.board_state.preview_piece(
-                    self.player_state.current_player,
-                    piece,
-                    position,
-                );
-                next_game_state.play_previewed_piece().ok()
+            .get(&player)
+            .map(|hand| {
+                PieceName::ALL
+                    .into_iter()
+                    .filter(|piece_name| hand.is_available(*piece_name))
+                    .collect()
             })
-            .next()
-            .is_some()
+            .unwrap_or_default()
     }
 
-    fn make_greedy_ai_move(&mut self) {
-        let all_moves = self
-            .player_state
-            .get_available_piece_rotations()
+    /// The result of the game, once it has ended
+    pub fn result(&self) -> Option<&GameResult> {
+        self.result.as_ref()
+    }
+
+    /// Zobrist hash of the position: the occupied cells, the pieces in each hand and the player to move
+    ///
+    /// Maintained incrementally as moves are played and undone. Identifies positions for caching and duplicate detection
+    pub fn hash(&self) -> u64 {
+        self.board_state.board.hash() ^ self.player_state.hash()
+    }
+
+    /// hashes the position from scratch, agrees with [GameState::hash]
+    pub fn recompute_hash(&self) -> u64 {
+        self.board_state.board.recompute_hash() ^ self.player_state.recompute_hash()
+    }
+
+    /// A player's score minus the best score among the other players
+    pub fn score_margin(&self, player: Player) -> i8 {
+        score_margin(&self.score, player)
+    }
+
+    /// The score that would result from a move, without playing it
+    pub fn score_after(&self, game_move: &Move) -> HashMap<Player, i8> {
+        let mut score = self.board_state.board.calculate_score_with(
+            &self.rules,
+            self.player_state.current_player,
+            game_move.cubes(),
+        );
+        score.retain(|player, _| self.player_state.players.contains_key(player));
+        score
+    }
+
+    /// Returns every legal move available to a player given the current board
+    pub fn legal_moves(&self, player: Player) -> Vec<Move> {
+        self.legal_moves_iter(player).collect()
+    }
+
+    /// Does the player have at least one legal move
+    ///
+    /// Checked after every turn, so it stops at the first legal placement without building moves
+    pub fn has_legal_move(&self, player: Player) -> bool {
+        let Some(hand) = self.player_state.players.get(&player) else {
+            return false;
+        };
+        let board = &self.board_state.board;
+        let empty_cells = board.empty_cells();
+
+        hand.get_available_piece_rotations()
             .into_iter()
-            .cartesian_product(self.board_state.board.get_available_positions().into_iter());
-
-        let best_move_resulting_gs = all_moves
-            .filter_map(|((piece_name, piece), position)| {
-                let mut next_game_state = self.clone();
-                next_game_state.apply_action(Action::SelectPiece(piece_name));
-                next_game_state.board_state.preview_piece(
-                    self.player_state.current_player,
-                    piece,
-                    position,
-                );
-                match next_game_state.play_previewed_piece() {
-                    Ok(_) => Some((
-                        next_game_state.score[&self.player_state.current_player]
-                            - next_game_state.score[&self.player_state.current_player.get_other()],
-                        next_game_state,
-                    )),
-                    Err(_) => None,
-                }
+            .any(|(_, _, piece)| {
+                empty_cells.iter().any(|position| {
+                    board.is_legal_placement(
+                        &self.rules,
+                        player,
+                        piece.coords.iter().map(|c| c + position),
+                    )
+                })
             })
-            .max_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    /// Whether a move is one of the player's [legal moves](GameState::legal_moves), without generating them
+    pub fn is_legal_move(&self, player: Player, game_move: &Move) -> bool {
+        let board = &self.board_state.board;
+        self.player_state
+            .players
+            .get(&player)
+            .is_some_and(|hand| hand.is_available(game_move.piece))
+            && game_move.piece.orientation(game_move.orientation_id) == Some(&game_move.orientation)
+            && board.empty_cells().contains(game_move.position)
+            && board.is_legal_placement(&self.rules, player, game_move.cubes())
+    }
+
+    fn legal_moves_iter(&self, player: Player) -> impl Iterator<Item = Move> + '_ {
+        legal_moves_on(
+            &self.board_state.board,
+            &self.rules,
+            self.player_state.players.get(&player),
+            player,
+        )
+    }
 
-        match best_move_resulting_gs {
-            Some((_score, next_gs)) => *self = next_gs,
-            None => self.apply_action(Action::PassTurn),
+    /// The legal move of a player placing cubes at exactly these positions, in any order
+    ///
+    /// Recovers the moves of turns recorded in the [History]
+    pub fn move_placing(&self, player: Player, cubes: &[Coord]) -> Option<Move> {
+        let mut placed = cubes.to_vec();
+        placed.sort_by_key(|c| (c.x, c.y, c.z));
+        self.legal_moves(player).into_iter().find(|game_move| {
+            let mut move_cubes = game_move.cubes();
+            move_cubes.sort_by_key(|c| (c.x, c.y, c.z));
+            move_cubes == placed
+        })
+    }
+
+    /// Plays a move for the current player
+    ///
+    /// The move is recorded in the history as if the player had selected, previewed and played the piece
+    pub fn play_move(&mut self, game_move: &Move) -> Result<(), ActionError> {
+        if self.game_ended {
+            return Err(ActionError::GameEnded);
+        }
+        if game_move.piece.orientation(game_move.orientation_id) != Some(&game_move.orientation) {
+            return Err(ActionError::OrientationMismatch {
+                piece: game_move.piece,
+                orientation_id: game_move.orientation_id,
+            });
+        }
+
+        let current_player = self.player_state.current_player;
+        let errors: Vec<CubeError> = self
+            .board_state
+            .check_piece_placement(
+                &self.rules,
+                current_player,
+                game_move.orientation.clone(),
+                game_move.position,
+            )
+            .iter()
+            .filter_map(|cube| cube.error)
+            .collect();
+        if !errors.is_empty() {
+            return Err(ActionError::InvalidPreview(errors));
+        }
+
+        self.select_piece(game_move.piece)?;
+        self.board_state.preview_piece(
+            &self.rules,
+            current_player,
+            game_move.orientation.clone(),
+            game_move.position,
+        );
+        self.play_previewed_piece()
+    }
+
+    /// Plays the move chosen by a strategy for the current player, passing if it chooses none
+    ///
+    /// The turn is recorded in the history as played by the AI
+    pub fn make_ai_move(&mut self, strategy: &dyn Strategy) -> Result<(), ActionError> {
+        if self.game_ended {
+            return Err(ActionError::GameEnded);
+        }
+
+        match strategy.choose_move(self) {
+            Some(game_move) => self.play_move(&game_move)?,
+            None => self.pass_turn(),
+        }
+        self.history.mark_last_by_ai();
+        Ok(())
+    }
+
+    /// AI moves are only available when playing against the AI
+    fn make_opponent_ai_move(&mut self, strategy: &dyn Strategy) -> Result<(), ActionError> {
+        match self.game_mode {
+            GameMode::VSGreedyAI(_) => self.make_ai_move(strategy),
+            game_mode => Err(ActionError::WrongGameMode(game_mode)),
         }
     }
 }
 
+/// The legal moves of a player holding `hand` on a board
+pub(crate) fn legal_moves_on<'a>(
+    board: &'a Board,
+    rules: &'a Rules,
+    hand: Option<&PlayerHandState>,
+    player: Player,
+) -> impl Iterator<Item = Move> + 'a {
+    let piece_rotations = hand
+        .map(|hand| hand.get_available_piece_rotations())
+        .unwrap_or_default();
+
+    piece_rotations
+        .into_iter()
+        .cartesian_product(board.get_available_positions())
+        .filter(move |((_, _, piece), position)| {
+            board.is_legal_placement(rules, player, piece.coords.iter().map(|c| c + position))
+        })
+        .map(|((piece, orientation_id, orientation), position)| Move {
+            piece,
+            orientation_id,
+            orientation: orientation.clone(),
+            position,
+        })
+}
+
+/// A player's score minus the best score among the other players
+pub(crate) fn score_margin(score: &HashMap<Player, i8>, player: Player) -> i8 {
+    let best_other = score
+        .iter()
+        .filter(|(p, _)| **p != player)
+        .map(|(_, score)| *score)
+        .max()
+        .unwrap_or_default();
+
+    score.get(&player).copied().unwrap_or_default() - best_other
+}
+
 #[cfg(test)]
 mod tests {
-    use nalgebra::Vector3;
+    use rand::RngCore;
+    use std::collections::HashMap;
 
     use crate::{
-        action::{Action, V3},
-        game_mode::GameMode,
+        action::{Action, ActionError, V3},
+        bitboard::Bitboard,
+        board::Cube,
+        coord::Coord,
+        endgame,
+        evaluation::Weights,
+        game_mode::{AIOpponent, Difficulty, FreeForAllMap, GameMode, PlayerCount, SolitaireMap},
+        game_move::Move,
+        game_result::{EndReason, GameResult, Outcome, SolitaireResult},
         game_state::GameState,
         piece::PieceName,
+        player::Player,
+        search,
+        strategy::Greedy,
     };
 
     #[test]
     fn test_multiple_actions() {
         let mut gs = GameState::new(GameMode::default());
 
-        gs.apply_action(Action::SelectPiece(PieceName::Corner));
-        gs.apply_action(Action::PreviewPiece(V3(Vector3::<f32>::new(0.0, 0.0, 0.0))));
-        gs.apply_action(Action::PlayPreviewedPiece);
+        gs.apply_action(Action::SelectPiece(PieceName::Corner))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
 
         let gs_str = serde_json::to_string(&gs).unwrap();
         println!("{}", gs_str)
     }
 
+    #[test]
+    fn test_action_errors() {
+        let mut gs = GameState::new(GameMode::default());
+
+        assert_eq!(
+            gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0)))),
+            Err(ActionError::NoPieceSelected)
+        );
+        assert_eq!(
+            gs.apply_action(Action::MakeGreedyAIMove),
+            Err(ActionError::WrongGameMode(GameMode::default()))
+        );
+
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        assert_eq!(
+            gs.apply_action(Action::PlayPreviewedPiece),
+            Err(ActionError::NoPiecePreviewed)
+        );
+
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 1, 0))))
+            .unwrap();
+        assert!(matches!(
+            gs.apply_action(Action::PlayPreviewedPiece),
+            Err(ActionError::InvalidPreview(_))
+        ));
+
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+        gs.apply_action(Action::PassTurn).unwrap();
+        assert_eq!(
+            gs.apply_action(Action::SelectPiece(PieceName::OneByTwo)),
+            Err(ActionError::PieceAlreadyPlayed(PieceName::OneByTwo))
+        );
+
+        gs.game_ended = true;
+        assert_eq!(
+            gs.apply_action(Action::PassTurn),
+            Err(ActionError::GameEnded)
+        );
+        gs.apply_action(Action::Reset).unwrap();
+        assert!(!gs.game_ended);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut gs = GameState::new(GameMode::default());
+        assert_eq!(
+            gs.apply_action(Action::Undo),
+            Err(ActionError::NothingToUndo)
+        );
+
+        gs.apply_action(Action::SelectPiece(PieceName::Corner))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+        let after_play = serde_json::to_value(&gs.board_state).unwrap();
+        let after_play_score = gs.score.clone();
+
+        gs.apply_action(Action::Undo).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert_eq!(gs.score, GameState::new(GameMode::default()).score);
+        assert!(gs.board_state.board.get_available_positions().len() == 80);
+        gs.apply_action(Action::SelectPiece(PieceName::Corner))
+            .unwrap();
+
+        gs.apply_action(Action::Redo).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P2);
+        assert_eq!(serde_json::to_value(&gs.board_state).unwrap(), after_play);
+        assert_eq!(gs.score, after_play_score);
+        assert_eq!(
+            gs.apply_action(Action::Redo),
+            Err(ActionError::NothingToRedo)
+        );
+    }
+
+    #[test]
+    fn test_undo_reverts_ai_reply() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(
+            crate::game_mode::TwoPlayerMap::Stairs.into(),
+        ));
+        let empty_cells = gs.board_state.board.get_available_positions().len();
+
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+
+        gs.apply_action(Action::Undo).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert_eq!(
+            gs.board_state.board.get_available_positions().len(),
+            empty_cells
+        );
+        assert_eq!(
+            gs.apply_action(Action::Undo),
+            Err(ActionError::NothingToUndo)
+        );
+
+        gs.apply_action(Action::Redo).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert!(gs.board_state.board.get_available_positions().len() < empty_cells - 2);
+    }
+
+    #[test]
+    fn test_undo_between_ais_reverts_one_turn() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(
+            crate::game_mode::TwoPlayerMap::Stairs.into(),
+        ));
+        for _ in 0..4 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        gs.apply_action(Action::Undo).unwrap();
+        assert_eq!(gs.history.turns().len(), 3);
+        assert_eq!(gs.player_state.current_player, Player::P2);
+
+        gs.apply_action(Action::Redo).unwrap();
+        assert_eq!(gs.history.turns().len(), 4);
+    }
+
+    #[test]
+    fn has_legal_move_agrees_with_legal_moves() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(
+            crate::game_mode::TwoPlayerMap::Tower.into(),
+        ));
+        while !gs.game_ended {
+            for player in [Player::P1, Player::P2] {
+                assert_eq!(
+                    gs.has_legal_move(player),
+                    !gs.legal_moves(player).is_empty()
+                );
+            }
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+        assert!(!gs.has_legal_move(Player::P1) && !gs.has_legal_move(Player::P2));
+    }
+
+    #[test]
+    fn is_legal_move_agrees_with_legal_moves() {
+        let mut gs = GameState::new(GameMode::default());
+        let first_moves = gs.legal_moves(Player::P1);
+        assert!(first_moves.iter().all(|m| gs.is_legal_move(Player::P1, m)));
+
+        gs.play_move(&first_moves[0]).unwrap();
+        let legal_moves = gs.legal_moves(Player::P1);
+        for game_move in &first_moves {
+            assert_eq!(
+                gs.is_legal_move(Player::P1, game_move),
+                legal_moves.contains(game_move)
+            );
+        }
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut gs = GameState::new(GameMode::default());
+
+        let moves = gs.legal_moves(Player::P1);
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.cubes().iter().all(|c| gs
+            .board_state
+            .board
+            .get(*c)
+            .is_some())));
+
+        gs.play_move(&moves[0]).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P2);
+
+        let played: Vec<_> = moves[0].cubes();
+        let replies = gs.legal_moves(Player::P2);
+        assert!(!replies.is_empty());
+        for reply in replies.iter().take(20) {
+            let mut next = gs.clone();
+            next.play_move(reply).unwrap();
+            assert!(reply.cubes().iter().all(|c| !played.contains(c)));
+        }
+
+        assert!(matches!(
+            gs.play_move(&moves[0]),
+            Err(ActionError::InvalidPreview(_))
+        ));
+    }
+
+    #[test]
+    fn play_move_rejects_an_orientation_of_another_piece() {
+        let mut gs = GameState::new(GameMode::default());
+        let moves = gs.legal_moves(Player::P1);
+        let one_by_four = moves
+            .iter()
+            .find(|m| m.piece == PieceName::OneByFour)
+            .unwrap();
+        let corner = Move {
+            piece: PieceName::Corner,
+            ..one_by_four.clone()
+        };
+
+        let (hash, empty_cells) = (gs.hash(), gs.board_state.board.empty_cells());
+        assert_eq!(
+            gs.play_move(&corner),
+            Err(ActionError::OrientationMismatch {
+                piece: PieceName::Corner,
+                orientation_id: one_by_four.orientation_id
+            })
+        );
+        assert_eq!(gs.hash(), hash);
+        assert_eq!(gs.board_state.board.empty_cells(), empty_cells);
+        assert!(gs.player_state.players[&Player::P1].is_available(PieceName::Corner));
+        assert!(gs.history.turns().is_empty());
+    }
+
+    #[test]
+    fn test_solitaire_turns() {
+        let mut gs = GameState::new(GameMode::Solitaire(SolitaireMap::FourByFiveByTwo));
+        assert_eq!(gs.board_state.board.empty_cells().count(), 40);
+
+        let moves = gs.legal_moves(Player::P1);
+        gs.play_move(&moves[0]).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert_eq!(gs.score.keys().collect::<Vec<_>>(), vec![&Player::P1]);
+        assert_eq!(
+            gs.apply_action(Action::PassTurn),
+            Err(ActionError::WrongGameMode(gs.game_mode))
+        );
+
+        while !gs.game_ended {
+            let moves = gs.legal_moves(Player::P1);
+            gs.play_move(&moves[0]).unwrap();
+        }
+        let empty_cells = gs.board_state.board.empty_cells().count();
+        assert_eq!(
+            gs.result.unwrap().outcome,
+            Outcome::Solitaire(match empty_cells {
+                0 => SolitaireResult::Completed,
+                empty_cells => SolitaireResult::Incomplete { empty_cells },
+            })
+        );
+    }
+
+    #[test]
+    fn test_solitaire_completed() {
+        let mut gs = GameState::new(GameMode::Solitaire(SolitaireMap::FourByFiveByTwo));
+        let hole = [Coord::new(0, 0, 0), Coord::new(0, 0, 1)];
+        let filled: Vec<Cube> = gs
+            .board_state
+            .board
+            .get_available_positions()
+            .into_iter()
+            .filter(|position| !hole.contains(position))
+            .map(|position| Cube {
+                player: Player::P1,
+                position,
+                error: None,
+            })
+            .collect();
+        gs.board_state.board.add_cubes(&filled);
+        for piece_name in PieceName::ALL {
+            if piece_name != PieceName::OneByTwo {
+                gs.player_state.remove_piece(Player::P1, piece_name);
+            }
+        }
+
+        gs.apply_action(Action::SelectPiece(PieceName::OneByTwo))
+            .unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(Coord::new(0, 0, 0))))
+            .unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+
+        assert!(gs.game_ended);
+        assert_eq!(
+            gs.result.as_ref().map(|r| (&r.outcome, r.reason)),
+            Some((
+                &Outcome::Solitaire(SolitaireResult::Completed),
+                EndReason::BoardFilled
+            ))
+        );
+
+        gs.apply_action(Action::Undo).unwrap();
+        assert!(!gs.game_ended);
+        assert_eq!(gs.result, None);
+    }
+
+    #[test]
+    fn test_free_for_all() {
+        let mut gs = GameState::new(GameMode::FreeForAll {
+            map: FreeForAllMap::Plaza,
+            player_count: PlayerCount::Three,
+        });
+        assert_eq!(gs.score.len(), 3);
+
+        for player in [Player::P1, Player::P2, Player::P3] {
+            assert_eq!(gs.player_state.current_player, player);
+            let moves = gs.legal_moves(player);
+            assert!(!moves.is_empty());
+            if player != Player::P1 {
+                // a player's first piece must touch a piece already in play
+                let occupied = gs
+                    .player_state
+                    .players_in_turn_order()
+                    .into_iter()
+                    .map(|p| gs.board_state.board.occupied_by(p))
+                    .fold(Bitboard::EMPTY, |acc, b| acc | b);
+                assert!(moves.iter().all(|m| {
+                    let cubes: Bitboard = m.cubes().into_iter().collect();
+                    !(cubes.neighbours() & occupied).is_empty()
+                }));
+            }
+            gs.play_move(&moves[0]).unwrap();
+        }
+        assert_eq!(gs.player_state.current_player, Player::P1);
+
+        // afterwards each player must touch their own pieces
+        let own = gs.board_state.board.occupied_by(Player::P1);
+        assert!(gs.legal_moves(Player::P1).iter().all(|m| {
+            let cubes: Bitboard = m.cubes().into_iter().collect();
+            !(cubes.neighbours() & own).is_empty()
+        }));
+
+        while !gs.game_ended {
+            let next_move = gs.legal_moves_iter(gs.player_state.current_player).next();
+            match next_move {
+                Some(m) => gs.play_move(&m).unwrap(),
+                None => gs.apply_action(Action::PassTurn).unwrap(),
+            }
+        }
+        let total: i8 = gs.score.values().sum();
+        assert!(total > 0);
+    }
+
+    #[test]
+    fn test_skips_player_without_moves() {
+        let mut gs = GameState::new(GameMode::default());
+        for piece_name in PieceName::ALL {
+            gs.player_state.remove_piece(Player::P2, piece_name);
+        }
+
+        let moves = gs.legal_moves(Player::P1);
+        gs.play_move(&moves[0]).unwrap();
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert!(!gs.game_ended);
+
+        // the game ends as soon as the last piece is played, without anyone passing
+        while !gs.game_ended {
+            let next_move = gs.legal_moves_iter(Player::P1).next().unwrap();
+            gs.play_move(&next_move).unwrap();
+            assert!(gs.game_ended || gs.player_state.current_player == Player::P1);
+        }
+
+        let result = gs.result.clone().unwrap();
+        assert_eq!(result.final_score, gs.score);
+        assert_eq!(result.outcome, Outcome::Winner(Player::P1));
+        assert!(matches!(
+            result.reason,
+            EndReason::AllPiecesPlayed | EndReason::NoLegalMoves
+        ));
+    }
+
+    #[test]
+    fn test_game_result_from_score() {
+        let players = [Player::P1, Player::P2, Player::P3];
+        let result = GameResult::from_score(
+            &players,
+            HashMap::from([(Player::P1, 4), (Player::P2, 6), (Player::P3, 6)]),
+            EndReason::NoLegalMoves,
+        );
+        assert_eq!(result.outcome, Outcome::Draw(vec![Player::P2, Player::P3]));
+
+        let result = GameResult::from_score(
+            &players[..2],
+            HashMap::from([(Player::P1, 4), (Player::P2, 3)]),
+            EndReason::BoardFilled,
+        );
+        assert_eq!(result.outcome, Outcome::Winner(Player::P1));
+    }
+
     #[test]
     fn test_ai_move() {
         let mut gs = GameState::new(GameMode::VSGreedyAI(
-            crate::game_mode::TwoPlayerMap::Pyramid,
+            crate::game_mode::TwoPlayerMap::Pyramid.into(),
         ));
 
-        gs.apply_action(Action::MakeGreedyAIMove);
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
         println!("{:?}", gs);
-        gs.apply_action(Action::MakeGreedyAIMove);
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
         println!("{:?}", gs);
     }
 
     #[test]
     fn test_ai_vs_ai() {
         let mut gs = GameState::new(GameMode::VSGreedyAI(
-            crate::game_mode::TwoPlayerMap::Pyramid,
+            crate::game_mode::TwoPlayerMap::Pyramid.into(),
         ));
 
         while !gs.game_ended {
             println!("{:?}", gs);
-            gs.make_greedy_ai_move();
+            gs.make_ai_move(&Greedy).unwrap();
             println!("made move");
         }
         println!("{:?}", gs);
     }
+
+    #[test]
+    fn ai_move_at_each_difficulty() {
+        let vs_ai = |difficulty| {
+            GameState::new(GameMode::VSGreedyAI(AIOpponent {
+                map: crate::game_mode::TwoPlayerMap::Tower,
+                difficulty,
+            }))
+        };
+
+        for difficulty in [Difficulty::Easy, Difficulty::Medium] {
+            let mut gs = vs_ai(difficulty);
+            gs.apply_action(Action::MakeAIMove).unwrap();
+            assert_eq!(gs.player_state.current_player, Player::P2);
+        }
+
+        let mut greedy = vs_ai(Difficulty::Medium);
+        let mut medium = vs_ai(Difficulty::Medium);
+        greedy.apply_action(Action::MakeGreedyAIMove).unwrap();
+        medium.apply_action(Action::MakeAIMove).unwrap();
+        assert_eq!(medium.hash(), greedy.hash());
+
+        // out of the book, hard searches two plies and the expert plays the solved line of the endgame
+        for difficulty in [Difficulty::Hard, Difficulty::Expert] {
+            let mut gs = vs_ai(difficulty);
+            while !endgame::is_solvable(&gs, endgame::DEFAULT_THRESHOLD) {
+                gs.make_ai_move(&Greedy).unwrap();
+            }
+            let expected_move = match difficulty {
+                Difficulty::Hard => search::alpha_beta(&gs, 2, &Weights::default())
+                    .best_move
+                    .unwrap(),
+                _ => endgame::solve(&gs).line[0].1.clone(),
+            };
+            let mut expected = gs.clone();
+            expected.play_move(&expected_move).unwrap();
+
+            gs.apply_action(Action::MakeAIMove).unwrap();
+            assert_eq!(gs.hash(), expected.hash());
+        }
+
+        assert_eq!(
+            GameState::default().apply_action(Action::MakeAIMove),
+            Err(ActionError::WrongGameMode(GameMode::default()))
+        );
+    }
+
+    #[test]
+    fn seeded_games_are_reproducible() {
+        let play = |seed| {
+            let mut gs = GameState::new(GameMode::VSGreedyAI(
+                crate::game_mode::TwoPlayerMap::Pyramid.into(),
+            ))
+            .with_seed(seed);
+            let mut hashes = vec![];
+            while !gs.game_ended {
+                gs.make_ai_move(&Greedy).unwrap();
+                hashes.push(gs.hash());
+            }
+            hashes
+        };
+
+        assert_eq!(play(7), play(7));
+
+        let gs = GameState::default();
+        let draw = |seed| gs.clone().with_seed(seed).rng().next_u64();
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
 }
@@ -0,0 +1,99 @@
+//! Contains [hints], the ranked candidate moves shown when a player asks for a hint
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    coord::Coord,
+    evaluation::{self, Weights},
+    game_move::Move,
+    game_state::GameState,
+};
+
+/// A suggested move for the current player
+#[derive(Serialize, Deserialize, TS, Clone, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/Hint.ts")]
+pub struct Hint {
+    pub game_move: Move,
+    /// positions of the cubes the move places, ready to be previewed
+    #[ts(type = "Array<[number,number,number]>")]
+    pub cubes: Vec<Coord>,
+    /// how much the player's score changes by playing the move
+    pub score_delta: i8,
+    /// the evaluation of the position after the move for the player, see [evaluation::evaluate]
+    pub evaluation: f32,
+}
+
+/// The `count` best moves for the current player, best first
+///
+/// Moves are ranked by their evaluation, equally evaluated moves by their score delta
+pub fn hints(state: &GameState, count: usize, weights: &Weights) -> Vec<Hint> {
+    if state.game_ended {
+        return vec![];
+    }
+
+    let player = state.player_state.current_player;
+    let score = state.score.get(&player).copied().unwrap_or_default();
+
+    let mut hints: Vec<Hint> = state
+        .legal_moves(player)
+        .into_iter()
+        .map(|game_move| Hint {
+            cubes: game_move.cubes(),
+            score_delta: state
+                .score_after(&game_move)
+                .get(&player)
+                .copied()
+                .unwrap_or_default()
+                - score,
+            evaluation: evaluation::evaluate_after(state, &game_move, player, weights),
+            game_move,
+        })
+        .collect();
+
+    // stable, so ties keep the order moves are generated in
+    hints.sort_by(|a, b| {
+        b.evaluation
+            .total_cmp(&a.evaluation)
+            .then(b.score_delta.cmp(&a.score_delta))
+    });
+    hints.truncate(count);
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::Action,
+        game_mode::{GameMode, TwoPlayerMap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn ranked_hints() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Pyramid.into()));
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+
+        let weights = Weights::default();
+        let hints = hints(&gs, 5, &weights);
+        assert_eq!(hints.len(), 5);
+        assert!(hints
+            .windows(2)
+            .all(|pair| pair[0].evaluation >= pair[1].evaluation));
+
+        let player = gs.player_state.current_player;
+        for hint in &hints {
+            let mut next = gs.clone();
+            next.play_move(&hint.game_move).unwrap();
+            assert_eq!(next.score[&player] - gs.score[&player], hint.score_delta);
+            assert_eq!(hint.cubes, hint.game_move.cubes());
+        }
+
+        // no other move is evaluated higher than the best hint
+        assert!(gs.legal_moves(player).iter().all(|game_move| {
+            evaluation::evaluate_after(&gs, game_move, player, &weights) <= hints[0].evaluation
+        }));
+    }
+}
@@ -0,0 +1,99 @@
+//! Contains [History] which records the turns taken during a game
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::{
+    board::Cube,
+    piece::{Piece, PieceName},
+    player::Player,
+};
+
+/// What happened during a turn
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/Turn.ts")]
+pub enum Turn {
+    /// a piece was played. The piece is recorded as it was in the player's hand so that it can be returned unchanged
+    Played {
+        piece_name: PieceName,
+        piece: Piece,
+        cubes: Vec<Cube>,
+    },
+    Passed,
+}
+
+/// A turn taken by a player, recorded in [History]
+#[derive(Serialize, Deserialize, TS, Clone, Debug)]
+#[ts(export, export_to = "pkg/types/HistoryEntry.ts")]
+pub struct HistoryEntry {
+    pub player: Player,
+    pub turn: Turn,
+    /// whether the turn was taken by an AI opponent
+    pub by_ai: bool,
+}
+
+/// Turns that can be undone and redone
+///
+/// Only turns (plays and passes) are recorded, selecting, rotating and previewing pieces are not
+#[derive(Serialize, Deserialize, TS, Clone, Debug, Default)]
+#[ts(export, export_to = "pkg/types/History.ts")]
+pub struct History {
+    /// turns taken so far, most recent last
+    past: Vec<HistoryEntry>,
+    /// turns that have been undone, most recently undone last
+    future: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// the turns taken so far, oldest first
+    pub fn turns(&self) -> &[HistoryEntry] {
+        &self.past
+    }
+
+    /// records a new turn. Any undone turns can no longer be redone
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.past.push(entry);
+        self.future.clear();
+    }
+
+    /// marks the most recent turn as taken by an AI opponent
+    pub fn mark_last_by_ai(&mut self) {
+        if let Some(entry) = self.past.last_mut() {
+            entry.by_ai = true
+        }
+    }
+
+    /// moves turns from the past to the future, returning them in the order they should be reverted
+    ///
+    /// If `include_ai_replies` is set, an AI turn is undone together with the player turn that preceded it
+    pub fn undo(&mut self, include_ai_replies: bool) -> Vec<HistoryEntry> {
+        let mut undone = vec![];
+        while let Some(entry) = self.past.pop() {
+            let by_ai = entry.by_ai;
+            self.future.push(entry.clone());
+            undone.push(entry);
+            // at most one AI reply is undone, so games between AIs are undone a turn at a time
+            if !(include_ai_replies && by_ai && self.past.last().is_some_and(|e| !e.by_ai)) {
+                break;
+            }
+        }
+        undone
+    }
+
+    /// moves turns from the future back to the past, returning them in the order they should be replayed
+    ///
+    /// If `include_ai_replies` is set, a player turn is redone together with the AI turn that followed it
+    pub fn redo(&mut self, include_ai_replies: bool) -> Vec<HistoryEntry> {
+        let mut redone = vec![];
+        while let Some(entry) = self.future.pop() {
+            let by_ai = entry.by_ai;
+            self.past.push(entry.clone());
+            redone.push(entry);
+            if !(include_ai_replies && !by_ai && self.future.last().is_some_and(|e| e.by_ai)) {
+                break;
+            }
+        }
+        redone
+    }
+}
@@ -5,12 +5,28 @@
 //! Defines game state, player actions, logic for updating game state
 
 pub mod action;
+pub mod bitboard;
 pub mod board;
 pub mod board_state;
+pub mod coord;
+pub mod endgame;
+pub mod evaluation;
 pub mod game_mode;
+pub mod game_move;
+pub mod game_record;
+pub mod game_result;
 pub mod game_state;
+pub mod hints;
+pub mod history;
+pub mod mcts;
+pub mod opening_book;
+pub mod parallel;
 pub mod piece;
 pub mod player;
 pub mod player_hand_state;
 pub mod player_state;
 pub mod rotation_axis;
+pub mod rules;
+pub mod search;
+pub mod strategy;
+pub mod zobrist;
@@ -0,0 +1,342 @@
+//! Contains [mcts], a Monte Carlo Tree Search for the AI opponent
+//!
+//! Each iteration walks down the tree by UCT, expands one untried move, plays the game out to the end and backs the result up the path
+
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use web_time::{Duration, Instant};
+
+use crate::{
+    evaluation::{self, Weights},
+    game_move::Move,
+    game_state::GameState,
+    player::Player,
+};
+
+/// Configures a Monte Carlo Tree Search
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq)]
+#[ts(export, export_to = "pkg/types/MctsConfig.ts")]
+pub struct MctsConfig {
+    pub budget: MctsBudget,
+    pub playout: Playout,
+    /// seeds the random number generator, the same seed and budget give the same move
+    #[ts(type = "number")]
+    pub seed: u64,
+    /// weight of the UCT exploration term
+    pub exploration: f32,
+    /// the evaluation used to pick moves in [Playout::GreedyBiased] playouts
+    #[serde(default)]
+    pub weights: Weights,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            budget: MctsBudget::Iterations(1000),
+            playout: Playout::GreedyBiased,
+            seed: 0,
+            exploration: std::f32::consts::SQRT_2,
+            weights: Weights::default(),
+        }
+    }
+}
+
+/// When the search stops
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/MctsBudget.ts")]
+pub enum MctsBudget {
+    /// a fixed number of iterations, reproducible for a given seed
+    Iterations(u32),
+    /// as many iterations as fit in the given number of milliseconds
+    TimeMs(u32),
+}
+
+/// How moves are chosen when playing a game out
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[ts(export, export_to = "pkg/types/Playout.ts")]
+pub enum Playout {
+    /// any legal move, uniformly at random
+    Random,
+    /// the best evaluated of a few random legal moves
+    GreedyBiased,
+}
+
+/// number of random moves compared by a [Playout::GreedyBiased] playout
+const GREEDY_SAMPLE: usize = 8;
+
+/// The result of a search
+#[derive(Debug, Clone)]
+pub struct MctsResult {
+    /// the most visited move, None if the player has no legal move
+    pub best_move: Option<Move>,
+    /// iterations completed
+    pub iterations: u32,
+    /// the share of the results won by the searching player under the best move
+    pub win_rate: f32,
+}
+
+/// Searches for the current player's best move
+pub fn mcts(state: &GameState, config: &MctsConfig) -> MctsResult {
+    let mut tree = Tree {
+        nodes: vec![Node::new(state.clone(), None, None)],
+        rng: ChaCha8Rng::seed_from_u64(config.seed),
+        config: *config,
+    };
+    tree.nodes[0].shuffle_untried(&mut tree.rng);
+
+    let deadline = match config.budget {
+        MctsBudget::TimeMs(ms) => Some(Instant::now() + Duration::from_millis(ms as u64)),
+        MctsBudget::Iterations(_) => None,
+    };
+
+    let mut iterations = 0;
+    while tree.nodes[0].has_moves() {
+        let done = match (config.budget, deadline) {
+            (MctsBudget::Iterations(n), _) => iterations >= n,
+            (_, Some(deadline)) => Instant::now() >= deadline,
+            _ => true,
+        };
+        // always complete one iteration so a move is found
+        if done && iterations > 0 {
+            break;
+        }
+        tree.iterate();
+        iterations += 1;
+    }
+
+    let player = state.player_state.current_player;
+    let best_child = tree.nodes[0]
+        .children
+        .iter()
+        .max_by_key(|child| tree.nodes[**child].visits)
+        .map(|child| &tree.nodes[*child]);
+
+    MctsResult {
+        best_move: best_child.and_then(|child| child.game_move.clone()),
+        iterations,
+        win_rate: best_child
+            .map(|child| child.rewards[player.index()] / child.visits as f32)
+            .unwrap_or_default(),
+    }
+}
+
+struct Node {
+    state: GameState,
+    parent: Option<usize>,
+    /// the move leading to this node from its parent
+    game_move: Option<Move>,
+    children: Vec<usize>,
+    /// legal moves not yet expanded into children
+    untried: Vec<Move>,
+    visits: u32,
+    /// the sum of the rewards backed up through this node, indexed by [Player::index]
+    rewards: [f32; 4],
+}
+
+impl Node {
+    fn new(state: GameState, parent: Option<usize>, game_move: Option<Move>) -> Self {
+        let untried = if state.game_ended {
+            vec![]
+        } else {
+            state.legal_moves(state.player_state.current_player)
+        };
+
+        Self {
+            state,
+            parent,
+            game_move,
+            children: vec![],
+            untried,
+            visits: 0,
+            rewards: [0.0; 4],
+        }
+    }
+
+    /// expansion pops moves off the end, shuffling makes it try them in a random order
+    fn shuffle_untried(&mut self, rng: &mut ChaCha8Rng) {
+        self.untried.shuffle(rng);
+    }
+
+    fn has_moves(&self) -> bool {
+        !self.untried.is_empty() || !self.children.is_empty()
+    }
+}
+
+struct Tree {
+    nodes: Vec<Node>,
+    rng: ChaCha8Rng,
+    config: MctsConfig,
+}
+
+impl Tree {
+    fn iterate(&mut self) {
+        let leaf = self.select(0);
+        let node = self.expand(leaf);
+        let rewards = self.playout(self.nodes[node].state.clone());
+        self.backpropagate(node, rewards);
+    }
+
+    /// follows the child with the best UCT value until reaching a node with untried moves or the end of the game
+    fn select(&self, mut index: usize) -> usize {
+        loop {
+            let node = &self.nodes[index];
+            if !node.untried.is_empty() || node.children.is_empty() {
+                return index;
+            }
+
+            // children are valued by the player choosing between them
+            let mover = node.state.player_state.current_player;
+            let log_visits = (node.visits as f32).ln();
+            index = *node
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    let uct_a = self.uct(&self.nodes[**a], mover, log_visits);
+                    let uct_b = self.uct(&self.nodes[**b], mover, log_visits);
+                    uct_a.total_cmp(&uct_b)
+                })
+                .expect("node has children");
+        }
+    }
+
+    fn uct(&self, child: &Node, mover: Player, log_parent_visits: f32) -> f32 {
+        let visits = child.visits as f32;
+        child.rewards[mover.index()] / visits
+            + self.config.exploration * (log_parent_visits / visits).sqrt()
+    }
+
+    /// adds a child for one of the node's untried moves
+    fn expand(&mut self, index: usize) -> usize {
+        let Some(game_move) = self.nodes[index].untried.pop() else {
+            return index;
+        };
+
+        let mut state = self.nodes[index].state.clone();
+        state
+            .play_move(&game_move)
+            .expect("legal moves can be played");
+
+        let mut child = Node::new(state, Some(index), Some(game_move));
+        child.shuffle_untried(&mut self.rng);
+        self.nodes.push(child);
+
+        let child_index = self.nodes.len() - 1;
+        self.nodes[index].children.push(child_index);
+        child_index
+    }
+
+    /// plays the game out and returns the reward for each player
+    fn playout(&mut self, mut state: GameState) -> [f32; 4] {
+        while !state.game_ended {
+            let player = state.player_state.current_player;
+            let moves = state.legal_moves(player);
+
+            let game_move = match self.config.playout {
+                Playout::Random => moves.choose(&mut self.rng),
+                Playout::GreedyBiased => moves
+                    .choose_multiple(&mut self.rng, GREEDY_SAMPLE)
+                    .max_by(|a, b| {
+                        let evaluate = |game_move| {
+                            evaluation::evaluate_after(
+                                &state,
+                                game_move,
+                                player,
+                                &self.config.weights,
+                            )
+                        };
+                        evaluate(a).total_cmp(&evaluate(b))
+                    }),
+            };
+
+            match game_move {
+                Some(game_move) => state
+                    .play_move(&game_move.clone())
+                    .expect("legal moves can be played"),
+                // the game ends once no player can move
+                None => break,
+            }
+        }
+
+        match &state.result {
+            Some(result) => Player::ALL.map(|player| result.reward(player)),
+            None => [0.0; 4],
+        }
+    }
+
+    fn backpropagate(&mut self, mut index: usize, rewards: [f32; 4]) {
+        loop {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            for (total, reward) in node.rewards.iter_mut().zip(rewards) {
+                *total += reward;
+            }
+
+            match node.parent {
+                Some(parent) => index = parent,
+                None => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::Action,
+        game_mode::{GameMode, SolitaireMap, TwoPlayerMap},
+    };
+
+    use super::*;
+
+    #[test]
+    fn seeded_search_is_reproducible() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Wall.into()));
+        while gs.legal_moves(gs.player_state.current_player).len() > 40 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        let config = MctsConfig {
+            budget: MctsBudget::Iterations(30),
+            ..MctsConfig::default()
+        };
+        let first = mcts(&gs, &config);
+
+        assert_eq!(first.iterations, 30);
+        assert!(first.best_move.is_some());
+        assert_eq!(mcts(&gs, &config).best_move, first.best_move);
+    }
+
+    #[test]
+    fn time_budget() {
+        let gs = GameState::new(GameMode::Solitaire(SolitaireMap::FourByFiveByTwo));
+        let result = mcts(
+            &gs,
+            &MctsConfig {
+                budget: MctsBudget::TimeMs(50),
+                playout: Playout::Random,
+                ..MctsConfig::default()
+            },
+        );
+
+        assert!(result.iterations >= 1);
+        assert!(gs
+            .legal_moves(Player::P1)
+            .contains(&result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn mcts_ai_move() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        gs.apply_action(Action::MakeMCTSAIMove(MctsConfig {
+            budget: MctsBudget::Iterations(10),
+            ..MctsConfig::default()
+        }))
+        .unwrap();
+
+        assert_eq!(gs.player_state.current_player, Player::P1);
+    }
+}
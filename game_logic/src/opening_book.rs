@@ -0,0 +1,290 @@
+//! Contains [OpeningBook], moves for the first turns of a game prepared ahead of time, and [generate] which prepares them
+//!
+//! Positions are looked up by [GameState::hash]. The hash does not cover the map, so each book holds the openings of a single map
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    coord::Coord,
+    evaluation::{self, Weights},
+    game_mode::{GameMode, TwoPlayerMap},
+    game_move::Move,
+    game_state::GameState,
+    parallel,
+    piece::PieceName,
+    rules::Rules,
+    search,
+};
+
+/// A move stored in an [OpeningBook]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BookMove {
+    pub piece: PieceName,
+    /// index into [PieceName::orientations]
+    pub orientation_id: usize,
+    pub position: Coord,
+    /// how often the move is chosen relative to the other moves of the position
+    pub weight: u32,
+}
+
+impl BookMove {
+    /// the move, None if the orientation does not exist
+    pub fn to_move(&self) -> Option<Move> {
+        self.piece
+            .orientation(self.orientation_id)
+            .map(|orientation| Move {
+                piece: self.piece,
+                orientation_id: self.orientation_id,
+                orientation: orientation.clone(),
+                position: self.position,
+            })
+    }
+}
+
+/// Weighted moves for positions of one map, keyed by position hash
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    pub positions: BTreeMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    /// The book moves stored for a position
+    pub fn moves(&self, state: &GameState) -> &[BookMove] {
+        self.positions
+            .get(&state.hash())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// One of the position's legal book moves, chosen by weight with [GameState::rng]
+    ///
+    /// None once the game has left the book
+    pub fn choose_move(&self, state: &GameState) -> Option<Move> {
+        let book_moves = self.moves(state);
+        if book_moves.is_empty() {
+            return None;
+        }
+
+        let player = state.player_state.current_player;
+        let candidates: Vec<(Move, u32)> = book_moves
+            .iter()
+            .filter_map(|book_move| Some((book_move.to_move()?, book_move.weight)))
+            .filter(|(game_move, _)| state.is_legal_move(player, game_move))
+            .collect();
+
+        candidates
+            .choose_weighted(&mut state.rng(), |(_, weight)| *weight)
+            .ok()
+            .map(|(game_move, _)| game_move.clone())
+    }
+}
+
+/// The books shipped for the built-in maps, generated with [BookConfig::default]
+const DEFAULT_BOOKS: &str = include_str!("../books/default_books.json");
+
+/// The shipped book for the map of a game, None for maps without one or for games played with variant rules
+pub fn default_book(state: &GameState) -> Option<&'static OpeningBook> {
+    static BOOKS: OnceLock<BTreeMap<TwoPlayerMap, OpeningBook>> = OnceLock::new();
+
+    let map = match state.game_mode {
+        GameMode::TwoPlayer(map) => map,
+        GameMode::VSGreedyAI(opponent) => opponent.map,
+        _ => return None,
+    };
+    if state.rules != Rules::default() {
+        return None;
+    }
+
+    BOOKS
+        .get_or_init(|| serde_json::from_str(DEFAULT_BOOKS).expect("default books are valid"))
+        .get(&map)
+}
+
+/// Configures [generate]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookConfig {
+    /// turns from the start of the game covered by the book
+    pub plies: u8,
+    /// plies searched to value each candidate move
+    pub depth: u8,
+    /// moves searched in each position, the best evaluated first
+    pub candidates: usize,
+    /// moves valued within this of the best move are kept, weighted by how close they are
+    pub tolerance: f32,
+    pub weights: Weights,
+}
+
+impl Default for BookConfig {
+    fn default() -> Self {
+        Self {
+            plies: 4,
+            depth: 3,
+            candidates: 4,
+            tolerance: 0.25,
+            weights: Weights::default(),
+        }
+    }
+}
+
+/// Builds a book for a map by searching every position reachable by book moves from the start
+///
+/// Slow with deep searches, meant to be run offline
+pub fn generate(map: TwoPlayerMap, config: &BookConfig) -> OpeningBook {
+    let mut book = OpeningBook::default();
+    let mut frontier = vec![GameState::new(GameMode::TwoPlayer(map))];
+
+    for _ in 0..config.plies {
+        let mut next_frontier = vec![];
+        for state in frontier {
+            // reached before by transposing moves
+            if state.game_ended || book.positions.contains_key(&state.hash()) {
+                continue;
+            }
+
+            let book_moves = book_moves(&state, config);
+            for (game_move, _) in &book_moves {
+                let mut next = state.clone();
+                next.play_move(game_move)
+                    .expect("legal moves can be played");
+                next_frontier.push(next);
+            }
+
+            book.positions.insert(
+                state.hash(),
+                book_moves
+                    .into_iter()
+                    .map(|(game_move, weight)| BookMove {
+                        piece: game_move.piece,
+                        orientation_id: game_move.orientation_id,
+                        position: game_move.position,
+                        weight,
+                    })
+                    .collect(),
+            );
+        }
+        frontier = next_frontier;
+    }
+
+    book
+}
+
+/// the best candidate moves of a position with their weights
+fn book_moves(state: &GameState, config: &BookConfig) -> Vec<(Move, u32)> {
+    let mover = state.player_state.current_player;
+
+    let mut candidates: Vec<(f32, Move)> = state
+        .legal_moves(mover)
+        .into_iter()
+        .map(|game_move| {
+            (
+                evaluation::evaluate_after(state, &game_move, mover, &config.weights),
+                game_move,
+            )
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    candidates.truncate(config.candidates);
+
+    let mut valued: Vec<(f32, Move)> = parallel::map(candidates, |(evaluation, game_move)| {
+        let mut next = state.clone();
+        next.play_move(&game_move)
+            .expect("legal moves can be played");
+        let value = if config.depth <= 1 || next.game_ended {
+            evaluation
+        } else {
+            let result = search::alpha_beta(&next, config.depth - 1, &config.weights);
+            // features are measured against the opponent, so values of two player games are symmetric
+            if next.player_state.current_player == mover {
+                result.value
+            } else {
+                -result.value
+            }
+        };
+        (value, game_move)
+    });
+    valued.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let Some(best) = valued.first().map(|(value, _)| *value) else {
+        return vec![];
+    };
+    valued
+        .into_iter()
+        .filter(|(value, _)| best - value <= config.tolerance)
+        .map(|(value, game_move)| {
+            let closeness = 1.0 - (best - value) / config.tolerance.max(f32::EPSILON);
+            (game_move, 1 + (closeness * 9.0).round() as u32)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game_mode::{AIOpponent, Difficulty},
+        player::Player,
+        strategy::{Greedy, Opening, Strategy},
+    };
+
+    use super::*;
+
+    #[test]
+    fn generated_book() {
+        let config = BookConfig {
+            plies: 2,
+            depth: 1,
+            candidates: 3,
+            ..BookConfig::default()
+        };
+        let book = generate(TwoPlayerMap::Tower, &config);
+        let start = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower));
+
+        let first_moves = book.moves(&start);
+        assert!(!first_moves.is_empty() && first_moves.len() <= 3);
+        assert!(first_moves.iter().any(|book_move| book_move.weight == 10));
+
+        let chosen = book.choose_move(&start).unwrap();
+        assert_eq!(book.choose_move(&start), Some(chosen.clone()));
+        assert!(start.legal_moves(Player::P1).contains(&chosen));
+
+        // the replies to every book move are in the book
+        for book_move in first_moves {
+            let mut next = start.clone();
+            next.play_move(&book_move.to_move().unwrap()).unwrap();
+            assert!(!book.moves(&next).is_empty());
+        }
+
+        let json = serde_json::to_string(&book).unwrap();
+        assert_eq!(serde_json::from_str::<OpeningBook>(&json).unwrap(), book);
+    }
+
+    #[test]
+    fn default_books() {
+        for map in TwoPlayerMap::ALL {
+            let start = GameState::new(GameMode::VSGreedyAI(AIOpponent {
+                map,
+                difficulty: Difficulty::Expert,
+            }));
+            let book = default_book(&start).unwrap();
+            let book_move = book.choose_move(&start).unwrap();
+
+            let opening = Opening {
+                book: None,
+                strategy: Greedy,
+            };
+            assert_eq!(opening.choose_move(&start), Some(book_move));
+        }
+
+        let variant = GameState::with_rules(
+            GameMode::TwoPlayer(TwoPlayerMap::Tower),
+            Rules {
+                touch: crate::rules::TouchRule::TouchAny,
+                ..Rules::default()
+            },
+        );
+        assert!(default_book(&variant).is_none());
+    }
+}
@@ -0,0 +1,51 @@
+//! Runs independent pieces of work, on a thread pool when the `parallel` feature is enabled
+//!
+//! WASM builds always run in sequence. Results come back in the order of the inputs either way, so callers behave identically with and without the feature
+
+/// Applies `f` to every item, returning the results in the order of the items
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub fn map<T, R>(items: Vec<T>, f: impl Fn(T) -> R + Sync + Send) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    use rayon::prelude::*;
+
+    items.into_par_iter().map(f).collect()
+}
+
+/// The number of items [map] works on at once
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub fn threads() -> usize {
+    rayon::current_num_threads()
+}
+
+/// Applies `f` to every item, returning the results in the order of the items
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+pub fn map<T, R>(items: Vec<T>, f: impl Fn(T) -> R) -> Vec<R> {
+    items.into_iter().map(f).collect()
+}
+
+/// The number of items [map] works on at once
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+pub fn threads() -> usize {
+    1
+}
+
+/// [Sync] when work runs on a thread pool, implemented by every type otherwise
+///
+/// Bounds types that are shared with the pool, so builds without the feature accept types that are not [Sync]
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+pub trait MaybeSync: Sync {}
+
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+
+/// [Sync] when work runs on a thread pool, implemented by every type otherwise
+///
+/// Bounds types that are shared with the pool, so builds without the feature accept types that are not [Sync]
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+pub trait MaybeSync {}
+
+#[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+impl<T: ?Sized> MaybeSync for T {}
@@ -1,23 +1,25 @@
 //! Contains [Piece], [PieceName]
 use itertools::Itertools;
-use nalgebra::{Rotation3, Vector3};
 use serde::{Deserialize, Serialize};
-use std::f32::consts::PI;
+use std::sync::OnceLock;
 use ts_rs::TS;
 
-use crate::rotation_axis::RotationAxis;
+use crate::{
+    coord::{self, Coord, Rotation},
+    rotation_axis::RotationAxis,
+};
 
-/// Represents a piece as a Vec of offsets as [`nalgebra::Vector3<f32>`] from [0,0,0]
-#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+/// Represents a piece as a Vec of offsets as [Coord] from [0,0,0]
+#[derive(Serialize, Deserialize, Debug, Clone, TS, PartialEq)]
 #[ts(export, export_to = "pkg/types/Piece.ts")]
 pub struct Piece {
     #[ts(type = "Array<[number,number,number]>")]
-    pub coords: Vec<Vector3<f32>>,
+    pub coords: Vec<Coord>,
 }
 
 impl Piece {
     /// applies vector translation to constituent cubes
-    pub fn get_moved_copy(&self, position: Vector3<f32>) -> Piece {
+    pub fn get_moved_copy(&self, position: Coord) -> Piece {
         Piece {
             coords: self.coords.iter().map(|c| c + position).collect(),
         }
@@ -26,36 +28,23 @@ impl Piece {
     /// Imagine picking up a [Polycube](https://https://en.wikipedia.org/wiki/Polycube) by one of the cubes
     ///
     /// The position of the cubes are expressed as offsets from this selected cube which is at the origin
-    pub fn set_origin(&mut self, new_origin: Vector3<f32>) {
+    pub fn set_origin(&mut self, new_origin: Coord) {
         self.coords = self.coords.iter().map(|a| a - new_origin).collect();
     }
 
     /// Rotates the piece about the input RotationAxis
     pub fn rotate(&mut self, rotation_axis: RotationAxis) {
-        let rotation = Rotation3::from_axis_angle(
-            &match rotation_axis {
-                RotationAxis::X => Vector3::x_axis(),
-                RotationAxis::Y => Vector3::y_axis(),
-            },
-            PI / 2.0,
-        );
+        let rotation = match rotation_axis {
+            RotationAxis::X => coord::quarter_turn_x(),
+            RotationAxis::Y => coord::quarter_turn_y(),
+        };
 
         *self = self.apply_rotation(rotation)
     }
 
-    fn apply_rotation(&self, rotation: Rotation3<f32>) -> Self {
+    fn apply_rotation(&self, rotation: Rotation) -> Self {
         Self {
-            coords: self
-                .coords
-                .iter()
-                .map(|coord| {
-                    let mut new_position = rotation * coord;
-                    new_position.apply(|component| {
-                        *component = component.round();
-                    });
-                    new_position
-                })
-                .collect(),
+            coords: self.coords.iter().map(|coord| rotation * coord).collect(),
         }
     }
 
@@ -63,47 +52,71 @@ impl Piece {
         Piece {
             coords: i
                 .iter()
-                .map(|[x, y, z]| Vector3::new(*x as f32, *y as f32, *z as f32))
+                .map(|[x, y, z]| Coord::new(*x as i32, *y as i32, *z as i32))
                 .collect(),
         }
     }
 
     /// Does this piece support a given position. All cubes must be supported. A cube can be supported by another cube in the same piece
-    pub fn supports(&self, position: &Vector3<f32>) -> bool {
-        self.coords
-            .iter()
-            .any(|c| *c == position - Vector3::<f32>::new(0.0, 1.0, 0.0))
+    pub fn supports(&self, position: &Coord) -> bool {
+        self.coords.iter().any(|c| *c == position - Coord::y())
     }
 
     /// Give all possible rotations of the current piece
     pub fn get_available_piece_rotations(&self) -> Vec<Piece> {
         // determines which side faces upwards (positive Y direction)
         let side_up_rotations = vec![
-            Rotation3::from_axis_angle(&Vector3::x_axis(), PI * 0.0),
-            Rotation3::from_axis_angle(&Vector3::x_axis(), PI * 0.5),
-            Rotation3::from_axis_angle(&Vector3::x_axis(), PI * 1.0),
-            Rotation3::from_axis_angle(&Vector3::x_axis(), PI * 1.5),
-            Rotation3::from_axis_angle(&Vector3::z_axis(), PI * 0.5),
-            Rotation3::from_axis_angle(&Vector3::z_axis(), PI * 1.5),
+            coord::repeat(coord::quarter_turn_x(), 0),
+            coord::repeat(coord::quarter_turn_x(), 1),
+            coord::repeat(coord::quarter_turn_x(), 2),
+            coord::repeat(coord::quarter_turn_x(), 3),
+            coord::repeat(coord::quarter_turn_z(), 1),
+            coord::repeat(coord::quarter_turn_z(), 3),
         ];
         // rotation about y-axis
         let y_axis_rotations = vec![
-            Rotation3::from_axis_angle(&Vector3::y_axis(), PI * 0.0),
-            Rotation3::from_axis_angle(&Vector3::y_axis(), PI * 0.5),
-            Rotation3::from_axis_angle(&Vector3::y_axis(), PI * 1.0),
-            Rotation3::from_axis_angle(&Vector3::y_axis(), PI * 1.5),
+            coord::repeat(coord::quarter_turn_y(), 0),
+            coord::repeat(coord::quarter_turn_y(), 1),
+            coord::repeat(coord::quarter_turn_y(), 2),
+            coord::repeat(coord::quarter_turn_y(), 3),
         ];
 
         side_up_rotations
             .into_iter()
-            .cartesian_product(y_axis_rotations.into_iter())
+            .cartesian_product(y_axis_rotations)
             .map(|(rot1, rot2)| self.apply_rotation(rot1).apply_rotation(rot2))
             .collect()
     }
+
+    /// Translates the piece so its lowest cube (by y, then x, then z) is at the origin and sorts the cubes in that order
+    ///
+    /// Two rotations of a piece occupy the same cells exactly when their normalized forms are equal
+    pub fn normalized(&self) -> Piece {
+        let mut coords = self.coords.clone();
+        coords.sort_by_key(|c| (c.y, c.x, c.z));
+        let anchor = coords.first().copied().unwrap_or_default();
+        Piece {
+            coords: coords.iter().map(|c| c - anchor).collect(),
+        }
+    }
+
+    /// The distinct rotations of the piece, normalized, in the order [Piece::get_available_piece_rotations] first produces them
+    pub fn distinct_orientations(&self) -> Vec<Piece> {
+        let mut orientations: Vec<Piece> = Vec::new();
+        for rotation in self.get_available_piece_rotations() {
+            let rotation = rotation.normalized();
+            if !orientations.contains(&rotation) {
+                orientations.push(rotation);
+            }
+        }
+        orientations
+    }
 }
 
 /// identifies pieces
-#[derive(Serialize, Deserialize, Hash, Eq, PartialEq, Copy, Clone, TS, Debug)]
+///
+/// ordered by declaration, so hands iterate their pieces in a stable order
+#[derive(Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, TS, Debug)]
 #[ts(export, export_to = "pkg/types/PieceName.ts")]
 #[serde(rename_all = "snake_case")]
 pub enum PieceName {
@@ -120,32 +133,93 @@ pub enum PieceName {
     Corner,
 }
 
-#[cfg(test)]
-mod tests {
-    use std::f32::consts::PI;
+impl PieceName {
+    /// every piece, in declaration order
+    pub const ALL: [PieceName; 11] = [
+        PieceName::OneByTwo,
+        PieceName::OneByThree,
+        PieceName::OneByFour,
+        PieceName::TwoByTwo,
+        PieceName::Z,
+        PieceName::T,
+        PieceName::L,
+        PieceName::ShortL,
+        PieceName::RightScrew,
+        PieceName::LeftScrew,
+        PieceName::Corner,
+    ];
+
+    /// position of the piece in [PieceName::ALL]
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// the piece as it is dealt into a player's hand
+    pub fn piece(&self) -> Piece {
+        Piece::from_vec_i8_array(match self {
+            PieceName::OneByTwo => vec![[0, 0, 0], [0, 0, 1]],
+            PieceName::OneByThree => vec![[0, 0, 0], [0, 0, 1], [0, 0, 2]],
+            PieceName::OneByFour => vec![[0, 0, 0], [0, 0, 1], [0, 0, 2], [0, 0, 3]],
+            PieceName::TwoByTwo => vec![[0, 0, 0], [0, 0, 1], [0, 1, 0], [0, 1, 1]],
+            PieceName::Z => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [0, 1, 2]],
+            PieceName::T => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [0, 0, 2]],
+            PieceName::L => vec![[0, 0, 0], [0, 0, 1], [0, 0, 2], [0, 1, 2]],
+            PieceName::ShortL => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1]],
+            PieceName::RightScrew => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [1, 1, 1]],
+            PieceName::LeftScrew => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [-1, 1, 1]],
+            PieceName::Corner => vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [1, 0, 1]],
+        })
+    }
+
+    /// The distinct orientations of the piece, normalized, indexed by orientation id
+    ///
+    /// Computed once and shared by search and the UI
+    pub fn orientations(&self) -> &'static [Piece] {
+        static ORIENTATIONS: OnceLock<[Vec<Piece>; 11]> = OnceLock::new();
+        &ORIENTATIONS.get_or_init(|| {
+            PieceName::ALL.map(|piece_name| piece_name.piece().distinct_orientations())
+        })[self.index()]
+    }
 
-    use nalgebra::{Rotation3, Vector3};
+    /// The orientation with the given id, if the piece has that many orientations
+    pub fn orientation(&self, orientation_id: usize) -> Option<&'static Piece> {
+        self.orientations().get(orientation_id)
+    }
 
-    use crate::piece::Piece;
+    /// The id of the orientation a rotated piece is in, if it is a rotation of this piece
+    pub fn orientation_id(&self, piece: &Piece) -> Option<usize> {
+        let normalized = piece.normalized();
+        self.orientations()
+            .iter()
+            .position(|orientation| *orientation == normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        coord::{self, Coord},
+        piece::{Piece, PieceName},
+    };
 
     #[test]
     fn set_origin() {
         let mut a = Piece {
-            coords: vec![Vector3::new(1.0, 1.0, 1.0), Vector3::new(2.0, 2.0, 2.0)],
+            coords: vec![Coord::new(1, 1, 1), Coord::new(2, 2, 2)],
         };
 
-        let new_origin = Vector3::new(1.0, 1.0, 1.0);
+        let new_origin = Coord::new(1, 1, 1);
 
         a.set_origin(new_origin);
 
-        assert_eq!(a.coords[0], Vector3::new(0.0, 0.0, 0.0));
-        assert_eq!(a.coords[1], Vector3::new(1.0, 1.0, 1.0))
+        assert_eq!(a.coords[0], Coord::new(0, 0, 0));
+        assert_eq!(a.coords[1], Coord::new(1, 1, 1))
     }
 
     #[test]
     fn v3_equality() {
-        let a: Vector3<f32> = Vector3::<f32>::new(0.0, 0.0, 0.0);
-        let b = Vector3::<f32>::new(0.0, 1.0, 0.0);
+        let a = Coord::new(0, 0, 0);
+        let b = Coord::new(0, 1, 0);
         assert_eq!(a, b - b);
     }
 
@@ -153,11 +227,41 @@ mod tests {
     fn vec_rounding() {
         let z_piece = Piece::from_vec_i8_array(vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [0, 1, 2]]);
 
-        dbg!(&z_piece);
+        let rotated_z_piece = z_piece.apply_rotation(coord::repeat(coord::quarter_turn_x(), 2));
 
-        let rotated_z_piece =
-            z_piece.apply_rotation(Rotation3::from_axis_angle(&Vector3::x_axis(), PI));
+        assert_eq!(
+            rotated_z_piece,
+            Piece::from_vec_i8_array(vec![[0, 0, 0], [0, 0, -1], [0, -1, -1], [0, -1, -2]])
+        );
+    }
 
-        dbg!(rotated_z_piece);
+    #[test]
+    fn rotations_are_exact() {
+        let z_piece = Piece::from_vec_i8_array(vec![[0, 0, 0], [0, 0, 1], [0, 1, 1], [0, 1, 2]]);
+
+        let mut rotated = z_piece.clone();
+        for _ in 0..4 {
+            rotated.rotate(crate::rotation_axis::RotationAxis::X);
+        }
+        assert_eq!(rotated, z_piece);
+        assert_eq!(z_piece.get_available_piece_rotations().len(), 24);
+    }
+
+    #[test]
+    fn distinct_orientations() {
+        let counts: Vec<usize> = PieceName::ALL
+            .iter()
+            .map(|piece_name| piece_name.orientations().len())
+            .collect();
+
+        assert_eq!(counts, vec![3, 3, 3, 3, 12, 12, 24, 12, 12, 12, 8]);
+
+        for piece_name in PieceName::ALL {
+            let orientations = piece_name.orientations();
+            for rotation in piece_name.piece().get_available_piece_rotations() {
+                let id = piece_name.orientation_id(&rotation.get_moved_copy(Coord::new(2, 1, 3)));
+                assert_eq!(orientations[id.unwrap()], rotation.normalized());
+            }
+        }
     }
 }
@@ -4,22 +4,36 @@ use ts_rs::TS;
 
 /// enum representing a player
 ///
-/// piece ownership, indexing into structures
-#[derive(Serialize, Deserialize, PartialEq, Eq, Copy, Clone, Default, Debug, TS, Hash)]
+/// piece ownership, indexing into structures. Ordered by turn order
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Default, Debug, TS, Hash,
+)]
 #[serde(rename_all = "lowercase")]
 #[ts(export, export_to = "pkg/types/Player.ts")]
 pub enum Player {
     #[default]
     P1,
     P2,
+    P3,
+    P4,
 }
 
 impl Player {
-    /// toggles between values
-    pub fn get_other(&self) -> Self {
+    /// every player, in turn order
+    pub const ALL: [Player; 4] = [Player::P1, Player::P2, Player::P3, Player::P4];
+
+    /// position of the player in per player arrays
+    pub fn index(&self) -> usize {
         match self {
-            Player::P1 => Player::P2,
-            Player::P2 => Player::P1,
+            Player::P1 => 0,
+            Player::P2 => 1,
+            Player::P3 => 2,
+            Player::P4 => 3,
         }
     }
+
+    /// the first `count` players in turn order
+    pub fn first(count: usize) -> Vec<Player> {
+        Player::ALL.into_iter().take(count).collect()
+    }
 }
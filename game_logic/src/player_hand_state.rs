@@ -1,12 +1,13 @@
 //! contains [PlayerHandState]
 
 use crate::{
+    action::ActionError,
+    coord::Coord,
     piece::{Piece, PieceName},
     rotation_axis::RotationAxis,
 };
-use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use ts_rs::TS;
 
 /// represents the pieces in a players hand, their availability, and which (if any) piece is selected by the player
@@ -15,8 +16,8 @@ use ts_rs::TS;
 pub struct PlayerHandState {
     /// The piece currently selected by the player, if one is selected
     selected_piece: Option<PieceName>,
-    /// The pieces in a player's hand. Pieces already played are represented by an Option::None
-    pieces: HashMap<PieceName, Option<Piece>>,
+    /// The pieces in a player's hand, in [PieceName] order. Pieces already played are represented by an Option::None
+    pieces: BTreeMap<PieceName, Option<Piece>>,
 }
 
 impl PlayerHandState {
@@ -29,33 +30,63 @@ impl PlayerHandState {
         });
     }
 
+    /// marks a piece as unavailable
+    pub fn remove_piece(&mut self, piece_name: PieceName) {
+        if let Some(entry) = self.pieces.get_mut(&piece_name) {
+            *entry = None
+        }
+    }
+
+    /// returns a previously played piece to the player's hand
+    pub fn return_piece(&mut self, piece_name: PieceName, piece: Piece) {
+        self.pieces.insert(piece_name, Some(piece));
+    }
+
     pub fn clear_selected_piece(&mut self) {
         self.selected_piece = None
     }
 
     /// sets a piece as selected by the player
-    pub fn set_selected_piece(&mut self, piece_name: PieceName) {
-        self.selected_piece = Some(piece_name)
+    ///
+    /// fails if the piece has already been played
+    pub fn set_selected_piece(&mut self, piece_name: PieceName) -> Result<(), ActionError> {
+        match self.pieces.get(&piece_name) {
+            Some(Some(_)) => {
+                self.selected_piece = Some(piece_name);
+                Ok(())
+            }
+            _ => Err(ActionError::PieceAlreadyPlayed(piece_name)),
+        }
     }
 
     /// sets which cube among the cubes comprising the selected piece is the representative cube.
     ///
     /// All other cubes are represented as offsets from this new origin.
-    pub fn set_selected_piece_origin(&mut self, new_origin: Vector3<f32>) {
-        if let Some(selected_piece_name) = self.selected_piece {
-            if let Some(Some(piece)) = self.pieces.get_mut(&selected_piece_name) {
-                piece.set_origin(new_origin)
-            }
-        }
+    pub fn set_selected_piece_origin(&mut self, new_origin: Coord) -> Result<(), ActionError> {
+        self.get_selected_piece_mut()
+            .map(|piece| piece.set_origin(new_origin))
     }
 
     /// Rotates the selected piece PI / 2 about the rotation axis in the positive direction
-    pub fn rotate_selected_piece(&mut self, rotation_axis: RotationAxis) {
-        self.selected_piece.map(|piece_name| {
-            self.pieces
-                .get_mut(&piece_name)
-                .map(|a| a.as_mut().map(|p| p.rotate(rotation_axis)))
-        });
+    pub fn rotate_selected_piece(
+        &mut self,
+        rotation_axis: RotationAxis,
+    ) -> Result<(), ActionError> {
+        self.get_selected_piece_mut()
+            .map(|piece| piece.rotate(rotation_axis))
+    }
+
+    fn get_selected_piece_mut(&mut self) -> Result<&mut Piece, ActionError> {
+        let piece_name = self.selected_piece.ok_or(ActionError::NoPieceSelected)?;
+        match self.pieces.get_mut(&piece_name) {
+            Some(Some(piece)) => Ok(piece),
+            _ => Err(ActionError::PieceAlreadyPlayed(piece_name)),
+        }
+    }
+
+    /// returns the name of the selected piece if a piece is selected
+    pub fn get_selected_piece_name(&self) -> Option<PieceName> {
+        self.selected_piece
     }
 
     /// returns the selected piece if a piece is selected
@@ -67,22 +98,37 @@ impl PlayerHandState {
         }
     }
 
-    /// returns all possible rotations of all available pieces in the player's hand
+    /// the number of cubes in the pieces remaining to be played
+    pub fn remaining_cubes(&self) -> usize {
+        self.pieces
+            .values()
+            .flatten()
+            .map(|piece| piece.coords.len())
+            .sum()
+    }
+
+    /// whether a piece remains to be played
+    pub fn is_available(&self, piece_name: PieceName) -> bool {
+        matches!(self.pieces.get(&piece_name), Some(Some(_)))
+    }
+
+    /// whether any piece remains to be played
+    pub fn has_available_pieces(&self) -> bool {
+        self.pieces.values().any(Option::is_some)
+    }
+
+    /// returns the distinct orientations of all available pieces in the player's hand, with their orientation ids
     ///
-    /// used when searching for available moves
-    pub fn get_available_piece_rotations(&self) -> Vec<(PieceName, Piece)> {
-        let available_pieces = self
-            .pieces
+    /// used when searching for available moves. Always in the same order, so the moves found are too
+    pub fn get_available_piece_rotations(&self) -> Vec<(PieceName, usize, &'static Piece)> {
+        self.pieces
             .iter()
-            .filter_map(|(name, piece)| piece.as_ref().map(|piece| (name, piece)));
-
-        available_pieces
-            .flat_map(|(name, piece)| {
-                piece
-                    .get_available_piece_rotations()
+            .filter(|(_, piece)| piece.is_some())
+            .flat_map(|(name, _)| {
+                name.orientations()
                     .iter()
-                    .map(|piece| (*name, piece.clone()))
-                    .collect::<Vec<_>>()
+                    .enumerate()
+                    .map(|(orientation_id, piece)| (*name, orientation_id, piece))
             })
             .collect()
     }
@@ -92,100 +138,10 @@ impl Default for PlayerHandState {
     fn default() -> Self {
         Self {
             selected_piece: Default::default(),
-            pieces: HashMap::from([
-                (
-                    PieceName::OneByTwo,
-                    Some(Piece::from_vec_i8_array(vec![[0, 0, 0], [0, 0, 1]])),
-                ),
-                (
-                    PieceName::OneByThree,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 0, 2],
-                    ])),
-                ),
-                (
-                    PieceName::OneByFour,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 0, 2],
-                        [0, 0, 3],
-                    ])),
-                ),
-                (
-                    PieceName::TwoByTwo,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 0],
-                        [0, 1, 1],
-                    ])),
-                ),
-                (
-                    PieceName::Z,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                        [0, 1, 2],
-                    ])),
-                ),
-                (
-                    PieceName::T,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                        [0, 0, 2],
-                    ])),
-                ),
-                (
-                    PieceName::L,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 0, 2],
-                        [0, 1, 2],
-                    ])),
-                ),
-                (
-                    PieceName::ShortL,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                    ])),
-                ),
-                (
-                    PieceName::RightScrew,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                        [1, 1, 1],
-                    ])),
-                ),
-                (
-                    PieceName::LeftScrew,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                        [-1, 1, 1],
-                    ])),
-                ),
-                (
-                    PieceName::Corner,
-                    Some(Piece::from_vec_i8_array(vec![
-                        [0, 0, 0],
-                        [0, 0, 1],
-                        [0, 1, 1],
-                        [1, 0, 1],
-                    ])),
-                ),
-            ]),
+            pieces: PieceName::ALL
+                .into_iter()
+                .map(|piece_name| (piece_name, Some(piece_name.piece())))
+                .collect(),
         }
     }
 }
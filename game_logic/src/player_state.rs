@@ -1,25 +1,85 @@
 //! manages information about players
 
 use crate::{
+    action::ActionError,
+    coord::Coord,
     game_mode::GameMode,
     piece::{Piece, PieceName},
     player::Player,
     player_hand_state::PlayerHandState,
     rotation_axis::RotationAxis,
+    zobrist,
 };
-use nalgebra::Vector3;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use ts_rs::TS;
+use std::collections::BTreeMap;
+use ts_rs::{Dependency, TS};
 
 /// manages information about players
 ///
 /// who is the current player, pieces in players' hands
-#[derive(Serialize, Deserialize, TS, Clone, Debug)]
-#[ts(export, export_to = "pkg/types/PlayerState.ts")]
+///
+/// Serialized as [SerializedPlayerState], the hash is recomputed when deserializing
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "SerializedPlayerState", into = "SerializedPlayerState")]
 pub(crate) struct PlayerState {
     pub(crate) current_player: Player,
-    pub(crate) players: HashMap<Player, PlayerHandState>,
+    pub(crate) players: BTreeMap<Player, PlayerHandState>,
+    /// Zobrist hash of the player to move and the pieces in each hand, kept up to date as they change
+    hash: u64,
+}
+
+/// The serialized form of [PlayerState]
+#[derive(Serialize, Deserialize, TS)]
+#[ts(export, rename = "PlayerState", export_to = "pkg/types/PlayerState.ts")]
+pub(crate) struct SerializedPlayerState {
+    current_player: Player,
+    players: BTreeMap<Player, PlayerHandState>,
+}
+
+impl From<SerializedPlayerState> for PlayerState {
+    fn from(serialized: SerializedPlayerState) -> Self {
+        let mut player_state = Self {
+            current_player: serialized.current_player,
+            players: serialized.players,
+            hash: 0,
+        };
+        player_state.hash = player_state.recompute_hash();
+        player_state
+    }
+}
+
+impl From<PlayerState> for SerializedPlayerState {
+    fn from(player_state: PlayerState) -> Self {
+        Self {
+            current_player: player_state.current_player,
+            players: player_state.players,
+        }
+    }
+}
+
+/// [PlayerState] is exported to TypeScript as its serialized form
+impl TS for PlayerState {
+    const EXPORT_TO: Option<&'static str> = SerializedPlayerState::EXPORT_TO;
+
+    fn decl() -> String {
+        SerializedPlayerState::decl()
+    }
+
+    fn name() -> String {
+        SerializedPlayerState::name()
+    }
+
+    fn inline() -> String {
+        SerializedPlayerState::inline()
+    }
+
+    fn dependencies() -> Vec<Dependency> {
+        SerializedPlayerState::dependencies()
+    }
+
+    fn transparent() -> bool {
+        SerializedPlayerState::transparent()
+    }
 }
 
 impl Default for PlayerState {
@@ -30,29 +90,94 @@ impl Default for PlayerState {
 
 impl PlayerState {
     pub fn new(game_mode: GameMode) -> Self {
-        Self {
+        SerializedPlayerState {
             current_player: Player::default(),
-            players: match game_mode {
-                GameMode::Solitaire(_) => HashMap::from([(Player::P1, PlayerHandState::default())]),
-                _ => HashMap::from([
-                    (Player::P1, PlayerHandState::default()),
-                    (Player::P2, PlayerHandState::default()),
-                ]),
-            },
+            players: game_mode
+                .players()
+                .into_iter()
+                .map(|player| (player, PlayerHandState::default()))
+                .collect(),
         }
+        .into()
     }
 
-    pub fn toggle_current_player(&mut self) {
-        if let Some(p) = self.players.get_mut(&self.current_player) {
-            p.clear_selected_piece()
+    /// Zobrist hash of the player to move and the pieces in each hand
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// hashes the player to move and the pieces in each hand from scratch
+    pub fn recompute_hash(&self) -> u64 {
+        self.players
+            .keys()
+            .map(|player| self.hand_hash(*player))
+            .fold(zobrist::side_to_move(self.current_player), |hash, key| {
+                hash ^ key
+            })
+    }
+
+    fn hand_hash(&self, player: Player) -> u64 {
+        self.players
+            .get(&player)
+            .map(|hand| {
+                PieceName::ALL
+                    .into_iter()
+                    .filter(|piece_name| hand.is_available(*piece_name))
+                    .fold(0, |hash, piece_name| {
+                        hash ^ zobrist::piece_in_hand(player, piece_name)
+                    })
+            })
+            .unwrap_or_default()
+    }
+
+    /// changes a player's hand, updating the hash for the pieces added or removed
+    fn update_hand(&mut self, player: Player, update: impl FnOnce(&mut PlayerHandState)) {
+        let before = self.hand_hash(player);
+        if let Some(hand) = self.players.get_mut(&player) {
+            update(hand)
         }
-        self.current_player = self.current_player.get_other();
+        self.hash ^= before ^ self.hand_hash(player);
+    }
+
+    fn set_side_to_move(&mut self, player: Player) {
+        self.hash ^= zobrist::side_to_move(self.current_player) ^ zobrist::side_to_move(player);
+        self.current_player = player;
     }
 
-    pub fn select_piece(&mut self, piece_name: PieceName) {
+    /// hands the turn to the next player in turn order. With a single player (solitaire) the current player keeps the turn
+    pub fn advance_current_player(&mut self) {
         if let Some(p) = self.players.get_mut(&self.current_player) {
-            p.set_selected_piece(piece_name)
+            p.clear_selected_piece()
         }
+        self.set_side_to_move(self.next_player());
+    }
+
+    /// the player after the current player in turn order
+    pub fn next_player(&self) -> Player {
+        let in_turn_order = Player::ALL
+            .into_iter()
+            .filter(|player| self.players.contains_key(player));
+
+        in_turn_order
+            .clone()
+            .skip_while(|player| *player != self.current_player)
+            .nth(1)
+            .or(in_turn_order.clone().next())
+            .unwrap_or(self.current_player)
+    }
+
+    /// the players taking part, in turn order
+    pub fn players_in_turn_order(&self) -> Vec<Player> {
+        Player::ALL
+            .into_iter()
+            .filter(|player| self.players.contains_key(player))
+            .collect()
+    }
+
+    pub fn select_piece(&mut self, piece_name: PieceName) -> Result<(), ActionError> {
+        self.players
+            .get_mut(&self.current_player)
+            .map_or(Ok(()), |p| p.set_selected_piece(piece_name))
     }
 
     pub fn clear_selected_piece(&mut self) {
@@ -61,23 +186,48 @@ impl PlayerState {
         }
     }
 
-    pub fn rotate_selected_piece(&mut self, rotation_axis: RotationAxis) {
-        if let Some(p) = self.players.get_mut(&self.current_player) {
-            p.rotate_selected_piece(rotation_axis)
-        }
+    pub fn rotate_selected_piece(
+        &mut self,
+        rotation_axis: RotationAxis,
+    ) -> Result<(), ActionError> {
+        self.players
+            .get_mut(&self.current_player)
+            .map_or(Ok(()), |p| p.rotate_selected_piece(rotation_axis))
     }
 
-    pub fn set_selected_piece_origin(&mut self, new_origin: Vector3<f32>) {
-        if let Some(p) = self.players.get_mut(&self.current_player) {
-            p.set_selected_piece_origin(new_origin)
-        }
+    pub fn set_selected_piece_origin(&mut self, new_origin: Coord) -> Result<(), ActionError> {
+        self.players
+            .get_mut(&self.current_player)
+            .map_or(Ok(()), |p| p.set_selected_piece_origin(new_origin))
     }
 
     pub fn play_selected_piece(&mut self) {
-        if let Some(p) = self.players.get_mut(&self.current_player) {
-            p.play_selected_piece()
+        self.update_hand(self.current_player, |p| p.play_selected_piece());
+        self.advance_current_player();
+    }
+
+    /// sets the current player, clearing any selected pieces
+    pub fn set_current_player(&mut self, player: Player) {
+        for p in self.players.values_mut() {
+            p.clear_selected_piece()
         }
-        self.toggle_current_player();
+        self.set_side_to_move(player);
+    }
+
+    /// returns a previously played piece to a player's hand
+    pub fn return_piece(&mut self, player: Player, piece_name: PieceName, piece: Piece) {
+        self.update_hand(player, |p| p.return_piece(piece_name, piece));
+    }
+
+    /// marks a piece in a player's hand as unavailable
+    pub fn remove_piece(&mut self, player: Player, piece_name: PieceName) {
+        self.update_hand(player, |p| p.remove_piece(piece_name));
+    }
+
+    pub fn get_selected_piece_name(&self) -> Option<PieceName> {
+        self.players
+            .get(&self.current_player)
+            .and_then(|p| p.get_selected_piece_name())
     }
 
     pub fn get_selected_piece(&mut self) -> Option<(Player, Piece)> {
@@ -90,8 +240,4 @@ impl PlayerState {
             _ => None,
         }
     }
-
-    pub fn get_available_piece_rotations(&self) -> Vec<(PieceName, Piece)> {
-        self.players.clone()[&self.current_player].get_available_piece_rotations()
-    }
 }
@@ -0,0 +1,152 @@
+//! Contains [Rules], the configurable parts of the game rules
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// The rule set a game is played with
+///
+/// The default is the standard rule set, variants are selected alongside the [GameMode](crate::game_mode::GameMode)
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[ts(export, export_to = "pkg/types/Rules.ts")]
+pub struct Rules {
+    pub touch: TouchRule,
+    pub support: SupportRule,
+    pub scoring: ScoringMethod,
+}
+
+/// Which pieces a new piece must touch
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[ts(export, export_to = "pkg/types/TouchRule.ts")]
+pub enum TouchRule {
+    /// each player's first piece must touch a piece in play, after this they must touch their own piece
+    #[default]
+    TouchOwn,
+    /// every piece must touch a piece in play, whoever played it
+    TouchAny,
+}
+
+impl TouchRule {
+    /// every touch rule, the standard one first
+    pub const ALL: [TouchRule; 2] = [TouchRule::TouchOwn, TouchRule::TouchAny];
+}
+
+/// What holds up the cubes of a new piece
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[ts(export, export_to = "pkg/types/SupportRule.ts")]
+pub enum SupportRule {
+    /// a cube may rest on the ground, a cube in play, or another cube of the same piece
+    #[default]
+    PieceSupport,
+    /// every cube must rest on the ground or a cube already in play
+    Gravity,
+}
+
+impl SupportRule {
+    /// every support rule, the standard one first
+    pub const ALL: [SupportRule; 2] = [SupportRule::PieceSupport, SupportRule::Gravity];
+}
+
+/// How the score is counted
+#[derive(Serialize, Deserialize, TS, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[ts(export, export_to = "pkg/types/ScoringMethod.ts")]
+pub enum ScoringMethod {
+    /// a point for each column whose top cube is the player's
+    #[default]
+    ColumnTops,
+    /// a point for each cube the player has on the board
+    CubeCount,
+}
+
+impl ScoringMethod {
+    /// every scoring method, the standard one first
+    pub const ALL: [ScoringMethod; 2] = [ScoringMethod::ColumnTops, ScoringMethod::CubeCount];
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, V3},
+        board::CubeError,
+        coord::Coord,
+        game_mode::{GameMode, TwoPlayerMap},
+        game_state::GameState,
+        piece::PieceName,
+        player::Player,
+    };
+
+    use super::*;
+
+    fn play(gs: &mut GameState, piece: PieceName, position: Coord) {
+        gs.apply_action(Action::SelectPiece(piece)).unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(position))).unwrap();
+        gs.apply_action(Action::PlayPreviewedPiece).unwrap();
+    }
+
+    fn preview_errors(gs: &mut GameState, piece: PieceName, position: Coord) -> Vec<CubeError> {
+        gs.apply_action(Action::SelectPiece(piece)).unwrap();
+        gs.apply_action(Action::PreviewPiece(V3(position))).unwrap();
+        gs.board_state
+            .previewed_piece
+            .iter()
+            .flatten()
+            .filter_map(|cube| cube.error)
+            .collect()
+    }
+
+    #[test]
+    fn touch_any() {
+        let mode = GameMode::TwoPlayer(TwoPlayerMap::Pyramid);
+        let touch_any = Rules {
+            touch: TouchRule::TouchAny,
+            ..Rules::default()
+        };
+
+        for rules in [Rules::default(), touch_any] {
+            let mut gs = GameState::with_rules(mode, rules);
+            play(&mut gs, PieceName::OneByTwo, Coord::new(0, 0, 0));
+            play(&mut gs, PieceName::OneByTwo, Coord::new(0, 0, 2));
+
+            // P1 touches only P2's piece
+            let errors = preview_errors(&mut gs, PieceName::OneByThree, Coord::new(0, 0, 4));
+            assert_eq!(errors.is_empty(), rules.touch == TouchRule::TouchAny);
+        }
+    }
+
+    #[test]
+    fn gravity() {
+        let gravity = Rules {
+            support: SupportRule::Gravity,
+            ..Rules::default()
+        };
+
+        // the default orientation of the corner stands one cube on another
+        let mut gs = GameState::default();
+        assert!(preview_errors(&mut gs, PieceName::Corner, Coord::new(0, 0, 0)).is_empty());
+
+        let mut gs = GameState::with_rules(GameMode::default(), gravity);
+        assert!(
+            preview_errors(&mut gs, PieceName::Corner, Coord::new(0, 0, 0))
+                .contains(&CubeError::Unsupported)
+        );
+        assert!(gs
+            .legal_moves(Player::P1)
+            .iter()
+            .all(|game_move| game_move.cubes().iter().all(|cube| cube.y == 0)));
+    }
+
+    #[test]
+    fn cube_count() {
+        let mut gs = GameState::with_rules(
+            GameMode::default(),
+            Rules {
+                scoring: ScoringMethod::CubeCount,
+                ..Rules::default()
+            },
+        );
+        play(&mut gs, PieceName::OneByTwo, Coord::new(0, 0, 0));
+        play(&mut gs, PieceName::OneByTwo, Coord::new(0, 1, 0));
+
+        assert_eq!(gs.score[&Player::P1], 2);
+        assert_eq!(gs.score[&Player::P2], 2);
+    }
+}
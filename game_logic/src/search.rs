@@ -0,0 +1,858 @@
+//! Contains [alpha_beta], the depth-limited search used by the search AI
+//!
+//! Games with more than two players are searched paranoid: every other player is assumed to play against the searching player
+//!
+//! [AnytimeSearch] deepens the search a step at a time, for callers that cannot wait for a whole search such as the browser's main thread
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use web_time::{Duration, Instant};
+
+use crate::{
+    bitboard::Bitboard,
+    evaluation::{self, Weights},
+    game_move::Move,
+    game_state::{legal_moves_on, GameState},
+    piece::PieceName,
+    player::Player,
+    zobrist::{Bound, Entry, TranspositionTable},
+};
+
+/// The deepest search [Action::MakeSearchAIMove](crate::action::Action::MakeSearchAIMove) runs, deeper requests are clamped to it
+///
+/// Each ply multiplies the work by the number of legal moves, deeper searches can block the browser for minutes
+pub const MAX_DEPTH: u8 = 4;
+
+/// The result of a search
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// the best move found, None if the player has no legal move
+    pub best_move: Option<Move>,
+    /// the evaluation for the searching player at the end of the best line
+    pub value: f32,
+    /// positions visited
+    pub nodes: u64,
+}
+
+/// Searches `depth` plies ahead for the current player's best move
+///
+/// Positions at the search horizon are valued by [evaluation::evaluate] for the searching player, and finished games by their result.
+/// Every legal move is searched, move ordering only changes how much of the tree is cut off
+///
+/// With the `parallel` feature the root moves are searched on a thread pool, finding the same move and value
+pub fn alpha_beta(state: &GameState, depth: u8, weights: &Weights) -> SearchResult {
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    if depth > 1 && !state.game_ended {
+        return parallel_alpha_beta(state, depth, weights);
+    }
+
+    alpha_beta_with_table(state, depth, weights, &mut TranspositionTable::new(1 << 12))
+}
+
+/// searches the ordered root moves on a thread pool, see [search_root_moves]
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+fn parallel_alpha_beta(state: &GameState, depth: u8, weights: &Weights) -> SearchResult {
+    let mut table = TranspositionTable::new(1);
+    let search = Search::new(state.player_state.current_player, weights, &mut table);
+    let moves = search.ordered_moves(state, true);
+    if moves.is_empty() {
+        // valued as the sequential search values a position without moves
+        return SearchResult {
+            best_move: None,
+            value: search.evaluate(state),
+            nodes: 1,
+        };
+    }
+
+    let moves = moves.into_iter().map(|(_, game_move)| game_move);
+    search_root_moves(state, depth, weights, moves.enumerate().collect())
+}
+
+/// searches the root moves with [parallel::map](crate::parallel::map), each with a table of its own
+///
+/// `moves` are the root moves with their place in the move ordering, and are started in the order given.
+/// The best value found so far is shared as alpha, so moves are cut off as in the sequential search
+#[cfg(any(test, all(feature = "parallel", not(target_arch = "wasm32"))))]
+fn search_root_moves(
+    state: &GameState,
+    depth: u8,
+    weights: &Weights,
+    moves: Vec<(usize, Move)>,
+) -> SearchResult {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    let player = state.player_state.current_player;
+    let root_moves = moves.len() as u64;
+
+    // bits of the best exact value of a root move
+    let shared_alpha = AtomicU32::new(f32::MIN.to_bits());
+    let search_move = |game_move: &Move, alpha: f32| {
+        let mut next = state.clone();
+        next.play_move(game_move)
+            .expect("legal moves can be played");
+        let mut table = TranspositionTable::new(1 << 12);
+        let mut search = Search::new(player, weights, &mut table);
+        let (value, _) = search.search(&next, depth - 1, alpha, f32::MAX);
+        (value, search.nodes)
+    };
+
+    let mut searched = crate::parallel::map(moves, |(rank, game_move)| {
+        let alpha = f32::from_bits(shared_alpha.load(Ordering::Relaxed));
+        let (value, nodes) = search_move(&game_move, alpha);
+        // a value above alpha is exact, one at or below it only bounds the move's value from above
+        let exact = value > alpha;
+        if exact {
+            let _ = shared_alpha.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |best| {
+                (value > f32::from_bits(best)).then_some(value.to_bits())
+            });
+        }
+        (rank, value, exact, game_move, nodes)
+    });
+    searched.sort_by_key(|(rank, ..)| *rank);
+
+    let mut nodes = 1 + root_moves + searched.iter().map(|(.., nodes)| nodes).sum::<u64>();
+    let best_value = searched
+        .iter()
+        .filter(|(_, _, exact, ..)| *exact)
+        .map(|(_, value, ..)| *value)
+        .fold(f32::MIN, f32::max);
+
+    // the sequential search plays the first root move reaching the best value. A move cut off
+    // at exactly that value may reach it too, so it is searched again with a full window
+    let mut best_move = None;
+    for (_, value, exact, game_move, _) in searched {
+        if value != best_value {
+            continue;
+        }
+        if !exact {
+            let (value, research_nodes) = search_move(&game_move, f32::MIN);
+            nodes += research_nodes;
+            if value != best_value {
+                continue;
+            }
+        }
+        best_move = Some(game_move);
+        break;
+    }
+
+    SearchResult {
+        value: best_value,
+        best_move,
+        nodes,
+    }
+}
+
+/// [alpha_beta], reusing the positions stored in a transposition table by earlier searches
+///
+/// Stored values are from the searching player's point of view under the given weights, clear the table before searching for another player or with other weights
+pub fn alpha_beta_with_table(
+    state: &GameState,
+    depth: u8,
+    weights: &Weights,
+    table: &mut TranspositionTable,
+) -> SearchResult {
+    let mut search = Search::new(state.player_state.current_player, weights, table);
+    let (value, best_move) = search.search(state, depth.max(1), f32::MIN, f32::MAX);
+
+    SearchResult {
+        best_move,
+        value,
+        nodes: search.nodes,
+    }
+}
+
+struct Search<'a> {
+    /// the player searching for a move
+    player: Player,
+    weights: &'a Weights,
+    table: &'a mut TranspositionTable,
+    nodes: u64,
+    /// positions expanded, positions found in the table are not counted
+    expanded: u64,
+    /// the search is aborted before expanding more positions than this, once a new position has been kept in `partial`
+    limit: u64,
+    /// positions completed while an aborted search is resumed, kept apart from the table so none of them is replaced
+    partial: Option<&'a mut HashMap<u64, Entry>>,
+    /// new positions kept in `partial`
+    stored: u64,
+    aborted: bool,
+}
+
+impl<'a> Search<'a> {
+    fn new(player: Player, weights: &'a Weights, table: &'a mut TranspositionTable) -> Self {
+        Self {
+            player,
+            weights,
+            table,
+            nodes: 0,
+            expanded: 0,
+            limit: u64::MAX,
+            partial: None,
+            stored: 0,
+            aborted: false,
+        }
+    }
+
+    /// the value of the position for the searching player and its best move
+    ///
+    /// Once aborted the value is meaningless, and nothing more is stored in the table
+    fn search(
+        &mut self,
+        state: &GameState,
+        depth: u8,
+        mut alpha: f32,
+        mut beta: f32,
+    ) -> (f32, Option<Move>) {
+        self.nodes += 1;
+        if depth == 0 || state.game_ended {
+            return (self.evaluate(state), None);
+        }
+
+        let hash = state.hash();
+        let (alpha_original, beta_original) = (alpha, beta);
+        let stored = self
+            .partial
+            .as_ref()
+            .and_then(|partial| partial.get(&hash))
+            .or_else(|| self.table.get(hash))
+            .cloned();
+        if let Some(entry) = stored.as_ref().filter(|entry| entry.depth >= depth) {
+            match entry.bound {
+                Bound::Exact => return (entry.value, entry.best_move.clone()),
+                Bound::Lower => alpha = alpha.max(entry.value),
+                Bound::Upper => beta = beta.min(entry.value),
+            }
+            if alpha >= beta {
+                return (entry.value, entry.best_move.clone());
+            }
+        }
+
+        // an aborted subtree stores nothing, so a search keeps at least one new position before it
+        // aborts. Searching again with the same limit then gets further instead of stopping at the same point
+        if self.expanded >= self.limit && self.stored > 0 {
+            self.aborted = true;
+            return (0.0, None);
+        }
+        self.expanded += 1;
+
+        let maximizing = state.player_state.current_player == self.player;
+        let mut moves = self.ordered_moves(state, maximizing);
+        if moves.is_empty() {
+            return (self.evaluate(state), None);
+        }
+
+        // the best move found by an earlier search of the position is tried first
+        if let Some(stored_move) = stored.and_then(|entry| entry.best_move) {
+            if let Some(index) = moves.iter().position(|(_, m)| *m == stored_move) {
+                let stored_move = moves.remove(index);
+                moves.insert(0, stored_move);
+            }
+        }
+
+        let mut replies = (depth == 1).then(|| Replies::new(state));
+        let mut best_value = if maximizing { f32::MIN } else { f32::MAX };
+        let mut best_move = None;
+        for (evaluation, game_move) in moves {
+            let cells: Bitboard = game_move.cubes().into_iter().collect();
+            let value = if replies
+                .as_mut()
+                .is_some_and(|replies| replies.avoiding(cells))
+            {
+                // the game goes on after the move, so its value at the horizon is its evaluation from ordering
+                self.nodes += 1;
+                evaluation
+            } else {
+                let mut next = state.clone();
+                next.play_move(&game_move)
+                    .expect("legal moves can be played");
+                self.search(&next, depth - 1, alpha, beta).0
+            };
+            if self.aborted {
+                return (best_value, best_move);
+            }
+
+            if maximizing {
+                if best_move.is_none() || value > best_value {
+                    best_value = value;
+                    best_move = Some(game_move);
+                }
+                alpha = alpha.max(value);
+            } else {
+                if best_move.is_none() || value < best_value {
+                    best_value = value;
+                    best_move = Some(game_move);
+                }
+                beta = beta.min(value);
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let entry = Entry {
+            hash,
+            depth,
+            value: best_value,
+            bound: if best_value <= alpha_original {
+                Bound::Upper
+            } else if best_value >= beta_original {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            },
+            best_move: best_move.clone(),
+        };
+        if let Some(partial) = self.partial.as_mut() {
+            if partial.insert(hash, entry.clone()).is_none() {
+                self.stored += 1;
+            }
+        }
+        self.table.insert(entry);
+
+        (best_value, best_move)
+    }
+
+    /// The legal moves of the player to move with the evaluation after each, best first for that player
+    ///
+    /// Searching the strongest moves first lets alpha-beta cut off more of the tree
+    fn ordered_moves(&self, state: &GameState, maximizing: bool) -> Vec<(f32, Move)> {
+        let mut moves: Vec<(f32, Move)> = state
+            .legal_moves(state.player_state.current_player)
+            .into_iter()
+            .map(|game_move| {
+                (
+                    evaluation::evaluate_after(state, &game_move, self.player, self.weights),
+                    game_move,
+                )
+            })
+            .collect();
+
+        if maximizing {
+            moves.sort_by(|a, b| b.0.total_cmp(&a.0));
+        } else {
+            moves.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+
+        moves
+    }
+
+    /// finished games are valued by their result, above or below any position still being played
+    fn evaluate(&self, state: &GameState) -> f32 {
+        if state.game_ended {
+            return state.score_margin(self.player) as f32 * RESULT_SCALE;
+        }
+        evaluation::evaluate(state, self.player, self.weights)
+    }
+}
+
+/// The value of a point of final score margin, larger than any evaluation of a position
+const RESULT_SCALE: f32 = 1.0e6;
+
+/// The cells of the legal moves of the players not to move, generated as they are needed
+///
+/// Adding cubes never makes another player's placement illegal once a piece is in play,
+/// so a move leaving the cells of one of these replies empty cannot end the game
+struct Replies<'a> {
+    found: Vec<Bitboard>,
+    remaining: Box<dyn Iterator<Item = Bitboard> + 'a>,
+}
+
+impl<'a> Replies<'a> {
+    fn new(state: &'a GameState) -> Self {
+        let board = &state.board_state.board;
+        let mover = state.player_state.current_player;
+        let remaining: Box<dyn Iterator<Item = Bitboard>> = if board.all_occupied().is_empty() {
+            Box::new(std::iter::empty())
+        } else {
+            Box::new(
+                state
+                    .player_state
+                    .players_in_turn_order()
+                    .into_iter()
+                    .filter(move |player| *player != mover)
+                    .flat_map(move |player| {
+                        legal_moves_on(
+                            board,
+                            &state.rules,
+                            state.player_state.players.get(&player),
+                            player,
+                        )
+                    })
+                    .map(|reply| reply.cubes().into_iter().collect()),
+            )
+        };
+
+        Self {
+            found: vec![],
+            remaining,
+        }
+    }
+
+    /// Whether some reply places no cube on the cells, every reply is looked at before answering no
+    fn avoiding(&mut self, cells: Bitboard) -> bool {
+        if self.found.iter().any(|reply| (*reply & cells).is_empty()) {
+            return true;
+        }
+        for reply in self.remaining.by_ref() {
+            self.found.push(reply);
+            if (reply & cells).is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// How long [anytime] searches for
+#[derive(Serialize, Deserialize, TS, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data")]
+#[ts(export, export_to = "pkg/types/SearchBudget.ts")]
+pub enum SearchBudget {
+    /// about this many milliseconds
+    TimeMs(u32),
+    /// until this many positions have been expanded
+    Nodes(u32),
+}
+
+/// positions expanded between checks of the clock
+const NODES_PER_CHECK: u64 = 64;
+
+/// Searches deeper and deeper until the budget runs out, see [AnytimeSearch]
+pub fn anytime(state: &GameState, weights: &Weights, budget: SearchBudget) -> SearchResult {
+    let mut search = AnytimeSearch::start(state, *weights);
+    match budget {
+        SearchBudget::Nodes(nodes) => {
+            search.step(nodes as u64);
+        }
+        SearchBudget::TimeMs(ms) => {
+            let deadline = Instant::now() + Duration::from_millis(ms as u64);
+            while search.step(NODES_PER_CHECK) && Instant::now() < deadline {}
+        }
+    }
+    search.result()
+}
+
+/// An iterative deepening alpha-beta search, run a step at a time
+///
+/// Each iteration searches one ply deeper than the last, trying the last iteration's best move first.
+/// The best move of the deepest completed iteration is always available, so the search can be stopped at any step
+pub struct AnytimeSearch {
+    state: GameState,
+    weights: Weights,
+    /// kept between steps and iterations, so an interrupted subtree is not searched again from scratch
+    table: TranspositionTable,
+    /// the positions completed under the root move being searched, until it has been searched.
+    /// Unlike the table nothing is replaced, so every step gets further
+    partial: HashMap<u64, Entry>,
+    /// the root moves, the best move of the last completed iteration first
+    moves: Vec<Move>,
+    /// the depth of the iteration in progress
+    iteration_depth: u8,
+    /// root moves searched in the iteration in progress
+    searched: usize,
+    /// the best value found in the iteration in progress, and the index of its move
+    iteration_best: Option<(f32, usize)>,
+    /// the best move of the deepest completed iteration, and its value
+    best: Option<(f32, Move)>,
+    /// the depth of the deepest completed iteration
+    depth: u8,
+    /// deeper iterations would search past the end of the game
+    max_depth: u8,
+    nodes: u64,
+}
+
+impl AnytimeSearch {
+    /// Starts a search for the current player's best move
+    ///
+    /// The first iteration, one ply deep, is completed immediately
+    pub fn start(state: &GameState, weights: Weights) -> Self {
+        let player = state.player_state.current_player;
+        let mut table = TranspositionTable::default();
+
+        let moves = if state.game_ended {
+            vec![]
+        } else {
+            Search::new(player, &weights, &mut table).ordered_moves(state, true)
+        };
+        let turns_left = state
+            .player_state
+            .players
+            .values()
+            .map(|hand| {
+                PieceName::ALL
+                    .into_iter()
+                    .filter(|piece_name| hand.is_available(*piece_name))
+                    .count()
+            })
+            .sum::<usize>();
+
+        Self {
+            best: moves.first().cloned(),
+            nodes: moves.len() as u64 + 1,
+            moves: moves.into_iter().map(|(_, game_move)| game_move).collect(),
+            state: state.clone(),
+            weights,
+            table,
+            partial: HashMap::new(),
+            iteration_depth: 2,
+            searched: 0,
+            iteration_best: None,
+            depth: 1,
+            max_depth: turns_left.min(u8::MAX as usize) as u8,
+        }
+    }
+
+    /// Searches until about `nodes` positions have been expanded, returns whether there is more to search
+    ///
+    /// An interrupted iteration continues on the next step
+    pub fn step(&mut self, nodes: u64) -> bool {
+        let mut search = Search::new(
+            self.state.player_state.current_player,
+            &self.weights,
+            &mut self.table,
+        );
+        search.limit = nodes;
+        search.partial = Some(&mut self.partial);
+
+        while !(self.moves.len() <= 1 || self.depth >= self.max_depth) {
+            let mut next = self.state.clone();
+            next.play_move(&self.moves[self.searched])
+                .expect("legal moves can be played");
+
+            let alpha = self.iteration_best.map_or(f32::MIN, |(value, _)| value);
+            let (value, _) = search.search(&next, self.iteration_depth - 1, alpha, f32::MAX);
+            if search.aborted {
+                break;
+            }
+
+            if self.iteration_best.is_none_or(|(best, _)| value > best) {
+                self.iteration_best = Some((value, self.searched));
+            }
+            self.searched += 1;
+            search.partial.as_mut().expect("set for the step").clear();
+
+            if self.searched == self.moves.len() {
+                let (value, index) = self.iteration_best.take().expect("moves were searched");
+                let game_move = self.moves.remove(index);
+                self.moves.insert(0, game_move.clone());
+                self.best = Some((value, game_move));
+                self.depth = self.iteration_depth;
+                self.iteration_depth += 1;
+                self.searched = 0;
+            }
+        }
+
+        self.nodes += search.nodes;
+        !self.is_done()
+    }
+
+    /// Whether every iteration that could change the result has completed
+    pub fn is_done(&self) -> bool {
+        self.moves.len() <= 1 || self.depth >= self.max_depth
+    }
+
+    /// The best move of the deepest completed iteration, None if the player has no legal move
+    pub fn best_move(&self) -> Option<Move> {
+        self.best.as_ref().map(|(_, game_move)| game_move.clone())
+    }
+
+    /// The depth of the deepest completed iteration
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Positions visited so far
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// The result of the deepest completed iteration
+    pub fn result(&self) -> SearchResult {
+        SearchResult {
+            best_move: self.best_move(),
+            value: self.best.as_ref().map_or(0.0, |(value, _)| *value),
+            nodes: self.nodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::Action,
+        endgame,
+        game_mode::{GameMode, TwoPlayerMap},
+        game_state::GameState,
+        strategy::Greedy,
+    };
+
+    use super::*;
+
+    #[test]
+    fn search_agrees_with_full_minimax() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Wall.into()));
+        while gs.legal_moves(gs.player_state.current_player).len() > 60 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        let weights = Weights::default();
+        let result = alpha_beta(&gs, 2, &weights);
+        assert!(result.best_move.is_some());
+
+        // plain minimax over every reply
+        let player = gs.player_state.current_player;
+        let evaluate_after = |state: &GameState, game_move: &Move| {
+            evaluation::evaluate_after(state, game_move, player, &weights)
+        };
+        let minimax = gs
+            .legal_moves(player)
+            .iter()
+            .map(|game_move| {
+                let mut next = gs.clone();
+                next.play_move(game_move).unwrap();
+                let value = evaluation::evaluate(&next, player, &weights);
+                if next.game_ended {
+                    return value;
+                }
+                let replies = next
+                    .legal_moves(next.player_state.current_player)
+                    .into_iter()
+                    .map(|reply| evaluate_after(&next, &reply));
+                if next.player_state.current_player == player {
+                    replies.max_by(f32::total_cmp).unwrap_or(value)
+                } else {
+                    replies.min_by(f32::total_cmp).unwrap_or(value)
+                }
+            })
+            .max_by(f32::total_cmp)
+            .unwrap();
+
+        assert_eq!(result.value, minimax);
+    }
+
+    /// plain minimax over every move, valuing finished games by their result
+    fn minimax(state: &GameState, depth: u8, player: Player, weights: &Weights) -> f32 {
+        if state.game_ended {
+            return state.score_margin(player) as f32 * RESULT_SCALE;
+        }
+        if depth == 0 {
+            return evaluation::evaluate(state, player, weights);
+        }
+
+        let current_player = state.player_state.current_player;
+        let values = state
+            .legal_moves(current_player)
+            .into_iter()
+            .map(|game_move| {
+                let mut next = state.clone();
+                next.play_move(&game_move).unwrap();
+                minimax(&next, depth - 1, player, weights)
+            });
+        let value = if current_player == player {
+            values.max_by(f32::total_cmp)
+        } else {
+            values.min_by(f32::total_cmp)
+        };
+        value.unwrap_or_else(|| evaluation::evaluate(state, player, weights))
+    }
+
+    #[test]
+    fn search_agrees_with_minimax_near_the_end() {
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower)).with_seed(2);
+        while !endgame::is_solvable(&gs, 40) {
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+
+        // moves one ply from the horizon end the game in some of these positions and not in others
+        let weights = Weights::default();
+        while !gs.game_ended {
+            let player = gs.player_state.current_player;
+            assert_eq!(
+                alpha_beta(&gs, 3, &weights).value,
+                minimax(&gs, 3, player, &weights)
+            );
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+    }
+
+    #[test]
+    fn finished_games_are_valued_by_their_result() {
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower)).with_seed(2);
+        while !endgame::is_solvable(&gs, 10) {
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+        let player = gs.player_state.current_player;
+        let solution = endgame::solve(&gs);
+
+        // searched to the end of the game, the search finds the solved result
+        let weights = Weights::default();
+        let result = alpha_beta(&gs, 12, &weights);
+        assert_eq!(result.value, solution.margin as f32 * RESULT_SCALE);
+        let mut next = gs.clone();
+        next.play_move(&result.best_move.unwrap()).unwrap();
+        assert_eq!(endgame::solve_for(&next, player).margin, solution.margin);
+
+        // the move the evaluation prefers loses by more
+        let mut preferred = gs.clone();
+        preferred
+            .play_move(&alpha_beta(&gs, 1, &weights).best_move.unwrap())
+            .unwrap();
+        assert!(endgame::solve_for(&preferred, player).margin < solution.margin);
+    }
+
+    #[test]
+    fn transposition_table_is_reused() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Wall.into()));
+        while gs.legal_moves(gs.player_state.current_player).len() > 30 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        let weights = Weights::default();
+        let mut table = TranspositionTable::default();
+        let first = alpha_beta_with_table(&gs, 3, &weights, &mut table);
+        let second = alpha_beta_with_table(&gs, 3, &weights, &mut table);
+
+        assert_eq!(first.value, alpha_beta(&gs, 3, &weights).value);
+        assert_eq!(second.value, first.value);
+        assert_eq!(second.best_move, first.best_move);
+        assert!(second.nodes < first.nodes);
+    }
+
+    #[test]
+    fn anytime_search_deepens_in_steps() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Wall.into()));
+        while gs.legal_moves(gs.player_state.current_player).len() > 30 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        let weights = Weights::default();
+        let mut search = AnytimeSearch::start(&gs, weights);
+        assert_eq!(search.depth(), 1);
+        assert_eq!(search.result().value, alpha_beta(&gs, 1, &weights).value);
+
+        // even the smallest steps make progress
+        let mut steps = 0;
+        while search.depth() < 2 {
+            assert!(search.best_move().is_some());
+            assert!(search.step(1));
+            steps += 1;
+        }
+        assert!(steps > 1);
+        assert_eq!(search.result().value, alpha_beta(&gs, 2, &weights).value);
+
+        // deeper iterations have subtrees larger than a step, and still complete
+        while search.depth() < 3 {
+            search.step(1);
+        }
+        assert_eq!(search.result().value, alpha_beta(&gs, 3, &weights).value);
+
+        let timed = anytime(&gs, &weights, SearchBudget::TimeMs(10));
+        assert!(gs
+            .legal_moves(gs.player_state.current_player)
+            .contains(&timed.best_move.unwrap()));
+    }
+
+    #[test]
+    fn root_search_matches_the_sequential_search() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Stairs.into()));
+        while gs.legal_moves(gs.player_state.current_player).len() > 40 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        }
+
+        // searched in parallel with the parallel feature
+        let weights = Weights::default();
+        let result = alpha_beta(&gs, 2, &weights);
+        let sequential =
+            alpha_beta_with_table(&gs, 2, &weights, &mut TranspositionTable::new(1 << 12));
+
+        assert_eq!(result.value, sequential.value);
+        assert_eq!(result.best_move, sequential.best_move);
+    }
+
+    #[test]
+    fn root_moves_match_the_sequential_search_in_any_order() {
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower)).with_seed(1);
+        for _ in 0..8 {
+            gs.make_ai_move(&Greedy).unwrap();
+        }
+        let weights = Weights::default();
+        let player = gs.player_state.current_player;
+        let mut table = TranspositionTable::new(1);
+        let moves: Vec<Move> = Search::new(player, &weights, &mut table)
+            .ordered_moves(&gs, true)
+            .into_iter()
+            .map(|(_, game_move)| game_move)
+            .collect();
+
+        // several moves reach the best value
+        let values: Vec<f32> = moves
+            .iter()
+            .map(|game_move| {
+                let mut next = gs.clone();
+                next.play_move(game_move).unwrap();
+                let mut table = TranspositionTable::new(1 << 12);
+                Search::new(player, &weights, &mut table)
+                    .search(&next, 1, f32::MIN, f32::MAX)
+                    .0
+            })
+            .collect();
+        let best = values.iter().copied().fold(f32::MIN, f32::max);
+        assert!(values.iter().filter(|value| **value == best).count() > 1);
+
+        // started last, the first of them is cut off at the best value and searched again
+        let mut reversed: Vec<(usize, Move)> = moves.into_iter().enumerate().collect();
+        reversed.reverse();
+        let result = search_root_moves(&gs, 2, &weights, reversed);
+        let sequential =
+            alpha_beta_with_table(&gs, 2, &weights, &mut TranspositionTable::new(1 << 12));
+
+        assert_eq!(result.value, sequential.value);
+        assert_eq!(result.best_move, sequential.best_move);
+    }
+
+    #[test]
+    fn position_without_moves_matches_the_sequential_search() {
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower)).with_seed(1);
+        let stuck = loop {
+            gs.make_ai_move(&Greedy).unwrap();
+            if let Some(player) = [Player::P1, Player::P2]
+                .into_iter()
+                .find(|player| !gs.has_legal_move(*player))
+            {
+                break player;
+            }
+        };
+        assert!(!gs.game_ended);
+        // only reached when built by hand, the player would be skipped in play
+        gs.player_state.set_current_player(stuck);
+
+        // searched in parallel with the parallel feature
+        let weights = Weights::default();
+        let result = alpha_beta(&gs, 2, &weights);
+        let sequential =
+            alpha_beta_with_table(&gs, 2, &weights, &mut TranspositionTable::new(1 << 12));
+
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.value, sequential.value);
+    }
+
+    #[test]
+    fn search_ai_move() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+        let empty_cells = gs.board_state.board.empty_cells().count();
+        gs.apply_action(Action::MakeSearchAIMove(2)).unwrap();
+
+        assert!(gs.board_state.board.empty_cells().count() < empty_cells);
+        assert_eq!(gs.player_state.current_player, Player::P1);
+        assert_eq!(
+            GameState::default().apply_action(Action::MakeSearchAIMove(2)),
+            Err(crate::action::ActionError::WrongGameMode(
+                GameMode::default()
+            ))
+        );
+    }
+}
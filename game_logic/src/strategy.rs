@@ -0,0 +1,298 @@
+//! Contains [Strategy], the interface AI players implement, and the registry they are looked up in by name
+//!
+//! A new bot implements [Strategy] and is added with [register], after which it can be played through [GameState::make_ai_move] or [Action::MakeStrategyAIMove](crate::action::Action::MakeStrategyAIMove)
+
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use std::{
+    collections::BTreeMap,
+    sync::{OnceLock, RwLock},
+};
+
+use crate::{
+    endgame,
+    evaluation::Weights,
+    game_mode::Difficulty,
+    game_move::Move,
+    game_state::{score_margin, GameState},
+    mcts::{self, MctsConfig},
+    opening_book::{self, OpeningBook},
+    parallel::{self, MaybeSync},
+    search,
+};
+
+/// Chooses moves for an AI player
+///
+/// With the `parallel` feature strategies are shared between threads, and must be [Sync]
+pub trait Strategy: MaybeSync {
+    /// The move to play for the current player, None to pass
+    fn choose_move(&self, state: &GameState) -> Option<Move>;
+}
+
+/// Plays the move with the best immediate score margin
+///
+/// Ties between equally scoring moves are broken by [GameState::rng]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        let player = state.player_state.current_player;
+
+        let scored = parallel::map(state.legal_moves(player), |game_move| {
+            (
+                score_margin(&state.score_after(&game_move), player),
+                game_move,
+            )
+        });
+        let best_moves: Vec<Move> = scored
+            .into_iter()
+            .max_set_by_key(|(margin, _)| *margin)
+            .into_iter()
+            .map(|(_, game_move)| game_move)
+            .collect();
+
+        best_moves.choose(&mut state.rng()).cloned()
+    }
+}
+
+/// Plays a uniformly random legal move drawn from [GameState::rng]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Random;
+
+impl Strategy for Random {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        state
+            .legal_moves(state.player_state.current_player)
+            .choose(&mut state.rng())
+            .cloned()
+    }
+}
+
+/// Plays the move found by an alpha-beta search, see [search::alpha_beta]
+#[derive(Debug, Clone, Copy)]
+pub struct AlphaBeta {
+    /// plies searched
+    pub depth: u8,
+    pub weights: Weights,
+}
+
+impl Strategy for AlphaBeta {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        search::alpha_beta(state, self.depth, &self.weights).best_move
+    }
+}
+
+/// Plays the move found by a Monte Carlo Tree Search, see [mcts::mcts]
+impl Strategy for MctsConfig {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        mcts::mcts(state, self).best_move
+    }
+}
+
+/// Plays perfectly once the game is small enough to solve, see [endgame::solve]. Until then plays as `strategy`
+#[derive(Debug, Clone, Copy)]
+pub struct Endgame<S> {
+    /// the most legal moves left, summed over every player, at which the game is solved
+    pub threshold: usize,
+    pub strategy: S,
+}
+
+impl<S: Strategy> Strategy for Endgame<S> {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        if endgame::is_solvable(state, self.threshold) {
+            endgame::solve(state)
+                .line
+                .into_iter()
+                .next()
+                .map(|(_, game_move)| game_move)
+        } else {
+            self.strategy.choose_move(state)
+        }
+    }
+}
+
+/// The strategy an AI opponent of a difficulty plays, see [Difficulty] for what each builds
+pub fn for_difficulty(difficulty: Difficulty) -> Box<dyn Strategy> {
+    let search = AlphaBeta {
+        depth: 2,
+        weights: Weights::default(),
+    };
+    match difficulty {
+        Difficulty::Easy => Box::new(Random),
+        Difficulty::Medium => Box::new(Greedy),
+        Difficulty::Hard => Box::new(Opening {
+            book: None,
+            strategy: search,
+        }),
+        Difficulty::Expert => Box::new(Opening {
+            book: None,
+            strategy: Endgame {
+                threshold: endgame::DEFAULT_THRESHOLD,
+                strategy: search,
+            },
+        }),
+    }
+}
+
+/// Plays from an opening book while the game is in it. Afterwards plays as `strategy`
+#[derive(Debug, Clone)]
+pub struct Opening<S> {
+    /// the book played from, None for the book shipped for the game's map, see [opening_book::default_book]
+    pub book: Option<OpeningBook>,
+    pub strategy: S,
+}
+
+impl<S: Strategy> Strategy for Opening<S> {
+    fn choose_move(&self, state: &GameState) -> Option<Move> {
+        let book = self
+            .book
+            .as_ref()
+            .or_else(|| opening_book::default_book(state));
+
+        book.and_then(|book| book.choose_move(state))
+            .or_else(|| self.strategy.choose_move(state))
+    }
+}
+
+/// Builds a registered strategy
+pub type StrategyConstructor = fn() -> Box<dyn Strategy>;
+
+fn registry() -> &'static RwLock<BTreeMap<String, StrategyConstructor>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, StrategyConstructor>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtin: [(&str, StrategyConstructor); 5] = [
+            ("greedy", || Box::new(Greedy)),
+            ("random", || Box::new(Random)),
+            ("alpha_beta", || {
+                Box::new(AlphaBeta {
+                    depth: 2,
+                    weights: Weights::default(),
+                })
+            }),
+            ("alpha_beta_endgame", || {
+                Box::new(Endgame {
+                    threshold: endgame::DEFAULT_THRESHOLD,
+                    strategy: AlphaBeta {
+                        depth: 2,
+                        weights: Weights::default(),
+                    },
+                })
+            }),
+            ("mcts", || Box::new(MctsConfig::default())),
+        ];
+        RwLock::new(
+            builtin
+                .into_iter()
+                .map(|(name, constructor)| (name.to_string(), constructor))
+                .collect(),
+        )
+    })
+}
+
+/// Adds a strategy to the registry, replacing any strategy registered under the same name
+pub fn register(name: &str, constructor: StrategyConstructor) {
+    registry()
+        .write()
+        .expect("strategy registry is not poisoned")
+        .insert(name.to_string(), constructor);
+}
+
+/// The strategy registered under a name
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    registry()
+        .read()
+        .expect("strategy registry is not poisoned")
+        .get(name)
+        .map(|constructor| constructor())
+}
+
+/// The names of every registered strategy, in alphabetical order
+pub fn names() -> Vec<String> {
+    registry()
+        .read()
+        .expect("strategy registry is not poisoned")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, ActionError},
+        game_mode::{GameMode, TwoPlayerMap},
+    };
+
+    use super::*;
+
+    /// plays the first legal move
+    struct FirstMove;
+
+    impl Strategy for FirstMove {
+        fn choose_move(&self, state: &GameState) -> Option<Move> {
+            state
+                .legal_moves(state.player_state.current_player)
+                .into_iter()
+                .next()
+        }
+    }
+
+    #[test]
+    fn registry() {
+        for name in [
+            "greedy",
+            "random",
+            "alpha_beta",
+            "alpha_beta_endgame",
+            "mcts",
+        ] {
+            assert!(names().contains(&name.to_string()));
+        }
+        assert!(by_name("first_move").is_none());
+
+        register("first_move", || Box::new(FirstMove));
+
+        let gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        assert_eq!(
+            by_name("first_move").unwrap().choose_move(&gs),
+            FirstMove.choose_move(&gs)
+        );
+    }
+
+    #[test]
+    fn strategy_ai_move() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        gs.apply_action(Action::MakeStrategyAIMove("greedy".to_string()))
+            .unwrap();
+
+        let mut greedy = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Tower.into()));
+        greedy.apply_action(Action::MakeGreedyAIMove).unwrap();
+        assert_eq!(gs.hash(), greedy.hash());
+
+        assert_eq!(
+            gs.apply_action(Action::MakeStrategyAIMove("unknown".to_string())),
+            Err(ActionError::UnknownStrategy("unknown".to_string()))
+        );
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[test]
+    fn strategies_need_not_be_sync() {
+        /// counts the moves it chose, which a strategy shared between threads could not
+        struct Counting(std::cell::Cell<u32>);
+
+        impl Strategy for Counting {
+            fn choose_move(&self, state: &GameState) -> Option<Move> {
+                self.0.set(self.0.get() + 1);
+                FirstMove.choose_move(state)
+            }
+        }
+
+        let counting = Counting(Default::default());
+        let mut gs = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Tower));
+        gs.make_ai_move(&counting).unwrap();
+        gs.make_ai_move(&counting).unwrap();
+        assert_eq!(counting.0.get(), 2);
+    }
+}
@@ -0,0 +1,230 @@
+//! Contains the Zobrist keys positions are hashed with, and [TranspositionTable]
+//!
+//! A position's hash is the XOR of the keys of every occupied cell, every piece still in a hand and the player to move.
+//! Playing or undoing a move only XORs the keys that changed, see [Board::hash](crate::board::Board::hash) and [GameState::hash](crate::game_state::GameState::hash)
+
+use crate::{bitboard::SIZE, coord::Coord, game_move::Move, piece::PieceName, player::Player};
+
+const CELLS: usize = SIZE * SIZE * SIZE;
+const PLAYERS: usize = Player::ALL.len();
+const PIECES: usize = PieceName::ALL.len();
+
+struct Keys {
+    cells: [[u64; CELLS]; PLAYERS],
+    pieces: [[u64; PIECES]; PLAYERS],
+    side_to_move: [u64; PLAYERS],
+}
+
+/// SplitMix64, generates the keys at compile time so every build hashes positions the same way
+const fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    let mut keys = Keys {
+        cells: [[0; CELLS]; PLAYERS],
+        pieces: [[0; PIECES]; PLAYERS],
+        side_to_move: [0; PLAYERS],
+    };
+
+    let mut player = 0;
+    while player < PLAYERS {
+        let mut cell = 0;
+        while cell < CELLS {
+            keys.cells[player][cell] = split_mix(&mut state);
+            cell += 1;
+        }
+        let mut piece = 0;
+        while piece < PIECES {
+            keys.pieces[player][piece] = split_mix(&mut state);
+            piece += 1;
+        }
+        keys.side_to_move[player] = split_mix(&mut state);
+        player += 1;
+    }
+
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+/// The key of a player's cube in a cell, cells off the board have no key
+pub fn cell(player: Player, coord: Coord) -> u64 {
+    crate::coord::to_index(coord)
+        .filter(|(x, y, z)| *x < SIZE && *y < SIZE && *z < SIZE)
+        .map(|(x, y, z)| KEYS.cells[player.index()][(y * SIZE + x) * SIZE + z])
+        .unwrap_or_default()
+}
+
+/// The key of a piece still in a player's hand
+pub fn piece_in_hand(player: Player, piece_name: PieceName) -> u64 {
+    KEYS.pieces[player.index()][piece_name.index()]
+}
+
+/// The key of the player to move
+pub fn side_to_move(player: Player) -> u64 {
+    KEYS.side_to_move[player.index()]
+}
+
+/// How a stored value relates to the true value of a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// the search failed high, the true value is at least the stored value
+    Lower,
+    /// the search failed low, the true value is at most the stored value
+    Upper,
+}
+
+/// A searched position
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub hash: u64,
+    /// plies searched below the position
+    pub depth: u8,
+    pub value: f32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+/// A fixed size table of searched positions, indexed by hash
+///
+/// When two positions share a slot the one searched deeper is kept
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable {
+    /// A table with room for at least `capacity` positions, rounded up to a power of two
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity.max(1).next_power_of_two()],
+        }
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        (hash as usize) & (self.slots.len() - 1)
+    }
+
+    /// The stored entry for a position, if it has not been replaced
+    pub fn get(&self, hash: u64) -> Option<&Entry> {
+        self.slots[self.slot(hash)]
+            .as_ref()
+            .filter(|entry| entry.hash == hash)
+    }
+
+    /// Stores an entry, unless its slot holds a deeper search of another position
+    pub fn insert(&mut self, entry: Entry) {
+        let slot = self.slot(entry.hash);
+        let keep_existing = matches!(
+            &self.slots[slot],
+            Some(existing) if existing.hash != entry.hash && existing.depth > entry.depth
+        );
+        if !keep_existing {
+            self.slots[slot] = Some(entry);
+        }
+    }
+
+    /// Forgets every stored position
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(1 << 16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::Action,
+        game_mode::{GameMode, TwoPlayerMap},
+        game_state::GameState,
+    };
+
+    use super::*;
+
+    #[test]
+    fn hash_is_maintained_by_play_and_undo() {
+        let mut gs = GameState::new(GameMode::VSGreedyAI(TwoPlayerMap::Pyramid.into()));
+        let start = gs.hash();
+        let mut hashes = vec![start];
+
+        for _ in 0..4 {
+            gs.apply_action(Action::MakeGreedyAIMove).unwrap();
+            assert_eq!(gs.hash(), gs.recompute_hash());
+            assert!(!hashes.contains(&gs.hash()));
+            hashes.push(gs.hash());
+        }
+
+        // survives the round trip through JSON
+        let json = serde_json::to_string(&gs).unwrap();
+        assert_eq!(
+            serde_json::from_str::<GameState>(&json).unwrap().hash(),
+            gs.hash()
+        );
+
+        while gs.apply_action(Action::Undo).is_ok() {}
+        assert_eq!(gs.hash(), start);
+    }
+
+    #[test]
+    fn transpositions_share_a_hash() {
+        let start = GameState::new(GameMode::TwoPlayer(TwoPlayerMap::Pyramid));
+        let along_z = |piece: PieceName, position: Coord| {
+            let orientation_id = piece.orientation_id(&piece.piece()).unwrap();
+            Move {
+                piece,
+                orientation_id,
+                orientation: piece.orientations()[orientation_id].clone(),
+                position,
+            }
+        };
+        let a = along_z(PieceName::OneByTwo, Coord::new(0, 0, 0));
+        let b = along_z(PieceName::OneByThree, Coord::new(1, 0, 0));
+
+        // P1 plays both pieces while P2 passes, in either order
+        let play = |first: &Move, second: &Move| {
+            let mut gs = start.clone();
+            gs.play_move(first).unwrap();
+            gs.apply_action(Action::PassTurn).unwrap();
+            gs.play_move(second).unwrap();
+            gs.hash()
+        };
+
+        assert_eq!(play(&a, &b), play(&b, &a));
+        assert_ne!(play(&a, &b), start.hash());
+    }
+
+    #[test]
+    fn table_keeps_deeper_entries() {
+        let mut table = TranspositionTable::new(2);
+        let entry = |hash, depth| Entry {
+            hash,
+            depth,
+            value: 0.0,
+            bound: Bound::Exact,
+            best_move: None,
+        };
+
+        table.insert(entry(1, 3));
+        table.insert(entry(3, 1));
+        assert!(table.get(3).is_none());
+        assert_eq!(table.get(1).unwrap().depth, 3);
+
+        table.insert(entry(1, 1));
+        assert_eq!(table.get(1).unwrap().depth, 1);
+
+        table.clear();
+        assert!(table.get(1).is_none());
+    }
+}